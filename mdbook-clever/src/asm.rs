@@ -0,0 +1,131 @@
+//! Parsing of the assembly examples in `clever-asm` code blocks.
+//!
+//! D-asm does not yet define the syntax of assembly, so only the parts of an example that the rest of the specification
+//! names are read: each non-blank line holds one instruction, as a mnemonic followed by operands separated by commas,
+//! such as `add r0, r1`.
+
+use pulldown_cmark::{CodeBlockKind, Event, Parser, Tag, TagEnd};
+
+/// The info string of code blocks holding assembly examples
+pub const ASM_LANG: &str = "clever-asm";
+
+/// Whether a code block's info string, which may have options after the language, marks it as an assembly example
+pub fn is_asm_block(lang: &str) -> bool {
+    lang.trim().split(',').next() == Some(ASM_LANG)
+}
+
+/// A `clever-asm` code block in a chapter
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AsmBlock {
+    /// The 1-based line of the chapter on which the block's contents start
+    pub line: usize,
+    pub text: String,
+}
+
+/// Finds the `clever-asm` code blocks in the markdown source of a chapter
+pub fn asm_blocks(content: &str) -> Vec<AsmBlock> {
+    let mut blocks = Vec::new();
+    let mut current: Option<AsmBlock> = None;
+
+    for (event, range) in Parser::new(content).into_offset_iter() {
+        match event {
+            Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(lang))) if is_asm_block(&lang) => {
+                // The contents start on the line after the opening fence
+                let line = content[..range.start].matches('\n').count() + 2;
+                current = Some(AsmBlock {
+                    line,
+                    text: String::new(),
+                });
+            }
+            Event::Text(text) => {
+                if let Some(block) = &mut current {
+                    block.text.push_str(&text);
+                }
+            }
+            Event::End(TagEnd::CodeBlock) => blocks.extend(current.take()),
+            _ => {}
+        }
+    }
+
+    blocks
+}
+
+/// Whether a name follows the pattern of register names, such as `r16`, `cr3` or `v2l`, rather than of a label
+pub fn looks_like_register(name: &str) -> bool {
+    let Some(rest) = ["cr", "r", "f", "v"]
+        .iter()
+        .find_map(|prefix| name.strip_prefix(prefix))
+    else {
+        return false;
+    };
+    let digits = rest.strip_suffix(['l', 'h']).unwrap_or(rest);
+
+    !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit())
+}
+
+/// An instruction in an assembly example
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Statement<'a> {
+    /// The 1-based line of the block the instruction is on
+    pub line: usize,
+    pub mnemonic: &'a str,
+    pub operands: Vec<&'a str>,
+}
+
+/// Parses the instructions of an assembly example, skipping blank lines
+pub fn statements(text: &str) -> Vec<Statement<'_>> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(idx, line)| {
+            let line = line.trim();
+            let (mnemonic, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let rest = rest.trim();
+            let operands = if rest.is_empty() {
+                Vec::new()
+            } else {
+                rest.split(',').map(str::trim).collect()
+            };
+
+            Statement {
+                line: idx + 1,
+                mnemonic,
+                operands,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn statements_split_mnemonic_and_operands() {
+        let stmts = statements("add r0, r1\n\n  und0\n");
+        assert_eq!(
+            stmts,
+            [
+                Statement {
+                    line: 1,
+                    mnemonic: "add",
+                    operands: vec!["r0", "r1"],
+                },
+                Statement {
+                    line: 3,
+                    mnemonic: "und0",
+                    operands: Vec::new(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn register_names_are_recognized() {
+        assert!(looks_like_register("r16"));
+        assert!(looks_like_register("cr3"));
+        assert!(looks_like_register("v2l"));
+        assert!(!looks_like_register("sum"));
+        assert!(!looks_like_register("r"));
+    }
+}
@@ -0,0 +1,150 @@
+use std::{
+    io::{self, Write as _},
+    path::{Path, PathBuf},
+};
+
+use mdbook::MDBook;
+
+use crate::{
+    config::Config,
+    registry::{CONTROL_FIELD, INSTRUCTION_ENCODING, Registry},
+    spec_lang::ast::BitRange,
+};
+
+mod c;
+mod description;
+mod rust;
+mod tablegen;
+
+const USAGE: &str = "Usage: mdbook-clever codegen <rust|c|tablegen|json|yaml> [--root <book directory>] [--output <file>]";
+
+/// The field of the instruction encoding holding the opcode
+pub const OPCODE_FIELD: &str = "opcode";
+
+fn usage_error(msg: impl core::fmt::Display) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, format!("{msg}\n{USAGE}"))
+}
+
+/// Runs the `codegen` subcommand, which writes source code generated from the definitions in the book
+pub fn run(mut args: impl Iterator<Item = String>) -> io::Result<()> {
+    let lang = args
+        .next()
+        .ok_or_else(|| usage_error("Expected a language"))?;
+
+    let mut root = PathBuf::from(".");
+    let mut output = None;
+
+    while let Some(arg) = args.next() {
+        match &*arg {
+            "--root" => {
+                root = args
+                    .next()
+                    .map(PathBuf::from)
+                    .ok_or_else(|| usage_error("Expected a directory after `--root`"))?
+            }
+            "--output" | "-o" => {
+                output = Some(
+                    args.next()
+                        .map(PathBuf::from)
+                        .ok_or_else(|| usage_error("Expected a file after `--output`"))?,
+                )
+            }
+            arg => return Err(usage_error(format!("Unexpected argument {arg}"))),
+        }
+    }
+
+    let book = MDBook::load(&root).map_err(io::Error::other)?;
+    let src_dir = book.root.join(&book.config.book.src);
+    let config = Config::load(&book.config)?;
+    let registry = Registry::collect(&book.book, &src_dir, &config)?;
+
+    let source = match &*lang {
+        "rust" => rust::RustGenerator::new(&registry).generate()?,
+        "c" => c::CGenerator::new(&registry).generate()?,
+        "tablegen" => tablegen::TableGenGenerator::new(&registry).generate()?,
+        "json" => description::to_json(&description::describe(&registry)),
+        "yaml" => description::to_yaml(&description::describe(&registry)),
+        lang => return Err(usage_error(format!("Unsupported language `{lang}`"))),
+    };
+
+    match output {
+        Some(path) => std::fs::write(path, source),
+        None => io::stdout().write_all(source.as_bytes()),
+    }
+}
+
+/// Writes the machine-readable description of the instruction set to `path`, as YAML if it ends in `.yaml` or `.yml`
+/// and as JSON otherwise
+pub fn write_description(registry: &Registry, path: &Path) -> io::Result<()> {
+    let description = description::describe(registry);
+    let source = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("yaml" | "yml") => description::to_yaml(&description),
+        _ => description::to_json(&description),
+    };
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, source)
+}
+
+/// Converts a name such as `float-ext`, `r0` or `READONLY` into an UpperCamelCase identifier
+pub fn camel_case(name: &str) -> String {
+    let mut ident = String::new();
+    for part in name.split(|c: char| !c.is_ascii_alphanumeric()) {
+        let mut chars = part.chars();
+        if let Some(first) = chars.next() {
+            ident.push(first.to_ascii_uppercase());
+            ident.extend(chars.map(|c| c.to_ascii_lowercase()));
+        }
+    }
+
+    if ident.starts_with(|c: char| c.is_ascii_digit()) {
+        ident.insert(0, '_');
+    }
+
+    ident
+}
+
+/// The variant name of each instruction, in the order of `registry.instructions`.
+///
+/// Mnemonics shared by several opcodes, such as `mov`, are suffixed with the opcode in octal.
+pub fn opcode_variants(registry: &Registry) -> Vec<String> {
+    registry
+        .instructions
+        .iter()
+        .map(|insn| {
+            let shared = registry
+                .instructions
+                .iter()
+                .filter(|i| i.mnemonic == insn.mnemonic)
+                .count()
+                > 1;
+            if shared {
+                format!("{}{:04o}", camel_case(&insn.mnemonic), insn.opcode)
+            } else {
+                camel_case(&insn.mnemonic)
+            }
+        })
+        .collect()
+}
+
+/// The bits of the opcode and control fields in the instruction encoding
+pub fn instruction_fields(registry: &Registry) -> io::Result<(BitRange, BitRange)> {
+    let enc = registry.encoding(INSTRUCTION_ENCODING).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("No `{INSTRUCTION_ENCODING}` encoding is defined"),
+        )
+    })?;
+    let field = |name: &str| {
+        enc.field(name).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("`{INSTRUCTION_ENCODING}` has no `{name}` field"),
+            )
+        })
+    };
+
+    Ok((field(OPCODE_FIELD)?, field(CONTROL_FIELD)?))
+}
@@ -0,0 +1,159 @@
+use std::{fmt::Write as _, io};
+
+use super::{instruction_fields, opcode_variants};
+use crate::{registry::Registry, spec_lang::ast::BitRange};
+
+/// Converts a name such as `float-ext` or `r0` into the upper case part of a macro name
+fn macro_case(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+pub struct CGenerator<'a> {
+    registry: &'a Registry,
+    out: String,
+}
+
+impl<'a> CGenerator<'a> {
+    pub fn new(registry: &'a Registry) -> Self {
+        Self {
+            registry,
+            out: String::new(),
+        }
+    }
+
+    pub fn generate(mut self) -> io::Result<String> {
+        let (opcode, control) = instruction_fields(self.registry)?;
+        self.write_all(opcode, control).map_err(io::Error::other)?;
+        Ok(self.out)
+    }
+
+    fn write_all(&mut self, opcode: BitRange, control: BitRange) -> core::fmt::Result {
+        writeln!(
+            self.out,
+            "/* @generated by mdbook-clever from the Clever-ISA specification. Do not edit. */\n"
+        )?;
+        writeln!(self.out, "#ifndef CLEVER_ISA_H")?;
+        writeln!(self.out, "#define CLEVER_ISA_H\n")?;
+
+        self.write_encoding(opcode, control)?;
+        self.write_opcodes()?;
+        self.write_registers()?;
+        self.write_reg_groups()?;
+        self.write_modifiers()?;
+
+        writeln!(self.out, "#endif /* CLEVER_ISA_H */")
+    }
+
+    fn write_encoding(&mut self, opcode: BitRange, control: BitRange) -> core::fmt::Result {
+        let mask = |range: BitRange| (1u32 << range.width()) - 1;
+
+        writeln!(self.out, "/* The first word of an instruction */")?;
+        writeln!(self.out, "#define CLEVER_OPCODE_SHIFT {}", opcode.lo)?;
+        writeln!(self.out, "#define CLEVER_OPCODE_MASK {:#x}", mask(opcode))?;
+        writeln!(self.out, "#define CLEVER_CONTROL_SHIFT {}", control.lo)?;
+        writeln!(self.out, "#define CLEVER_CONTROL_MASK {:#x}", mask(control))?;
+        writeln!(
+            self.out,
+            "#define CLEVER_ENCODE_INSN(opcode, h) \\\n    ((unsigned short)((((opcode) & CLEVER_OPCODE_MASK) << CLEVER_OPCODE_SHIFT) | (((h) & CLEVER_CONTROL_MASK) << CLEVER_CONTROL_SHIFT)))"
+        )?;
+        writeln!(
+            self.out,
+            "#define CLEVER_INSN_OPCODE(word) (((word) >> CLEVER_OPCODE_SHIFT) & CLEVER_OPCODE_MASK)"
+        )?;
+        writeln!(
+            self.out,
+            "#define CLEVER_INSN_CONTROL(word) (((word) >> CLEVER_CONTROL_SHIFT) & CLEVER_CONTROL_MASK)\n"
+        )
+    }
+
+    fn write_opcodes(&mut self) -> core::fmt::Result {
+        let variants = opcode_variants(self.registry);
+
+        writeln!(self.out, "/* Opcodes */")?;
+        for (insn, variant) in self.registry.instructions.iter().zip(&variants) {
+            writeln!(
+                self.out,
+                "#define CLEVER_OPCODE_{} {:#05x} /* {} (X-{}) */",
+                macro_case(variant),
+                insn.opcode,
+                insn.mnemonic,
+                insn.extension_name()
+            )?;
+        }
+        writeln!(self.out)
+    }
+
+    fn write_registers(&mut self) -> core::fmt::Result {
+        writeln!(self.out, "/* Register numbers */")?;
+        for reg in &self.registry.registers {
+            writeln!(
+                self.out,
+                "#define CLEVER_REG_{} {}",
+                macro_case(&reg.name),
+                reg.number
+            )?;
+        }
+        for reg in &self.registry.registers {
+            for alias in &reg.aliases {
+                writeln!(
+                    self.out,
+                    "#define CLEVER_REG_{} CLEVER_REG_{}",
+                    macro_case(alias),
+                    macro_case(&reg.name)
+                )?;
+            }
+        }
+        writeln!(self.out)
+    }
+
+    fn write_reg_groups(&mut self) -> core::fmt::Result {
+        if self.registry.reg_groups.is_empty() {
+            return Ok(());
+        }
+
+        writeln!(
+            self.out,
+            "/* Inline assembly constraint codes, from D-toolchain */"
+        )?;
+        for group in &self.registry.reg_groups {
+            writeln!(
+                self.out,
+                "#define CLEVER_CONSTRAINT_{} {:?} /* requires {} */",
+                macro_case(&group.name),
+                group.constraint,
+                group.feature
+            )?;
+        }
+        writeln!(self.out)
+    }
+
+    fn write_modifiers(&mut self) -> core::fmt::Result {
+        if self.registry.asm_modifiers.is_empty() {
+            return Ok(());
+        }
+
+        writeln!(
+            self.out,
+            "/* Inline assembly operand modifiers, from D-toolchain */"
+        )?;
+        for modifier in &self.registry.asm_modifiers {
+            writeln!(
+                self.out,
+                "#define CLEVER_MODIFIER_{} {:?} /* for {}; requires {} */",
+                macro_case(&modifier.name),
+                modifier.name,
+                modifier.groups.join(", "),
+                modifier.feature
+            )?;
+        }
+        writeln!(self.out)
+    }
+}
@@ -0,0 +1,206 @@
+//! The machine-readable description of the instruction set, documented by `isa-description.schema.json`.
+
+use serde_json::{Map, Value, json};
+
+use crate::{
+    registry::{Instruction, Registry, compare_versions},
+    spec_lang::ast::BitRange,
+};
+
+/// The version of the description format, incremented whenever a field is removed or changes meaning
+pub const SCHEMA_VERSION: u32 = 1;
+
+fn bits(name: &str, range: BitRange) -> Value {
+    json!({ "name": name, "hi": range.hi, "lo": range.lo })
+}
+
+fn operands(registry: &Registry, insn: &Instruction) -> Value {
+    let group = registry.op_group(insn);
+
+    (1..=insn.operands.unwrap_or(0))
+        .map(|pos| {
+            let operand = group.and_then(|g| g.operands.iter().find(|op| op.pos == pos));
+            let properties = insn
+                .operand_properties
+                .iter()
+                .find(|(op, _)| *op == pos)
+                .map(|(_, props)| props)
+                .or(operand.map(|op| &op.properties));
+
+            json!({
+                "position": pos,
+                "name": operand.map(|op| &op.name),
+                "properties": properties.cloned().unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+/// Builds the description of every extension, instruction, register, exception and encoding in the registry
+pub fn describe(registry: &Registry) -> Value {
+    let version = registry
+        .extension_defs
+        .iter()
+        .filter_map(|def| def.version.as_deref())
+        .max_by(|a, b| compare_versions(a, b));
+
+    let extensions: Vec<Value> = registry
+        .extension_defs
+        .iter()
+        .map(|def| {
+            json!({
+                "name": def.name,
+                "version": def.version,
+                "requires": def.requires,
+                "chapter": def.chapter,
+            })
+        })
+        .collect();
+
+    let instructions: Vec<Value> = registry
+        .instructions
+        .iter()
+        .map(|insn| {
+            json!({
+                "opcode": insn.opcode,
+                "mnemonic": insn.mnemonic,
+                "aliases": insn.aliases,
+                "extension": insn.extension_name(),
+                "version": registry.chapter_version(&insn.chapter),
+                "operands": operands(registry, insn),
+                "properties": insn.properties,
+                "control": insn.control,
+                "op-group": registry.op_group(insn).map(|g| &g.name),
+                "chapter": insn.chapter,
+            })
+        })
+        .collect();
+
+    let registers: Vec<Value> = registry
+        .registers
+        .iter()
+        .map(|reg| {
+            json!({
+                "name": reg.name,
+                "number": reg.number,
+                "aliases": reg.aliases,
+                "properties": reg.properties,
+                "fields": reg.fields.iter().map(|(name, range)| bits(name, *range)).collect::<Vec<_>>(),
+                "unspecified-fields": reg.unspecified_fields,
+                "version": registry.chapter_version(&reg.chapter),
+                "chapter": reg.chapter,
+            })
+        })
+        .collect();
+
+    let exceptions: Vec<Value> = registry
+        .exceptions
+        .iter()
+        .map(|ex| {
+            json!({
+                "name": ex.name,
+                "vector": ex.vector,
+                "code": ex.code,
+                "description": ex.description,
+                "version": registry.chapter_version(&ex.chapter),
+                "chapter": ex.chapter,
+            })
+        })
+        .collect();
+
+    let encodings: Vec<Value> = registry
+        .encodings
+        .iter()
+        .map(|enc| {
+            json!({
+                "label": enc.label,
+                "width": enc.width,
+                "fields": enc.fields.iter().map(|(name, range)| bits(name, *range)).collect::<Vec<_>>(),
+                "fixed": enc.fixed.iter().map(|(value, range)| {
+                    json!({ "value": value, "hi": range.hi, "lo": range.lo })
+                }).collect::<Vec<_>>(),
+                "chapter": enc.chapter,
+            })
+        })
+        .collect();
+
+    json!({
+        "schema": SCHEMA_VERSION,
+        "version": version,
+        "extensions": extensions,
+        "instructions": instructions,
+        "registers": registers,
+        "exceptions": exceptions,
+        "encodings": encodings,
+    })
+}
+
+fn write_yaml_map(out: &mut String, map: &Map<String, Value>, indent: usize) {
+    for (key, value) in map {
+        out.push_str(&" ".repeat(indent));
+        out.push_str(key);
+        out.push(':');
+        write_yaml_value(out, value, indent);
+    }
+}
+
+fn write_yaml_seq(out: &mut String, seq: &[Value], indent: usize) {
+    for value in seq {
+        match value {
+            Value::Object(map) if !map.is_empty() => {
+                // The first key of the mapping goes on the same line as the `-`
+                let mut item = String::new();
+                write_yaml_map(&mut item, map, indent + 2);
+                out.push_str(&" ".repeat(indent));
+                out.push_str("- ");
+                out.push_str(&item[indent + 2..]);
+            }
+            value => {
+                out.push_str(&" ".repeat(indent));
+                out.push('-');
+                write_yaml_value(out, value, indent);
+            }
+        }
+    }
+}
+
+/// Writes a value following a `key:` or `-`, including the line break
+fn write_yaml_value(out: &mut String, value: &Value, indent: usize) {
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            out.push('\n');
+            write_yaml_map(out, map, indent + 2);
+        }
+        Value::Array(seq) if !seq.is_empty() => {
+            out.push('\n');
+            write_yaml_seq(out, seq, indent + 2);
+        }
+        Value::Object(_) => out.push_str(" {}\n"),
+        Value::Array(_) => out.push_str(" []\n"),
+        // JSON scalars, including escaped strings, are also valid YAML flow scalars
+        scalar => {
+            out.push(' ');
+            out.push_str(&scalar.to_string());
+            out.push('\n');
+        }
+    }
+}
+
+/// Formats the description as YAML
+pub fn to_yaml(description: &Value) -> String {
+    let mut out = String::from(
+        "# @generated by mdbook-clever from the Clever-ISA specification. Do not edit.\n",
+    );
+    match description {
+        Value::Object(map) => write_yaml_map(&mut out, map, 0),
+        value => write_yaml_value(&mut out, value, 0),
+    }
+    out
+}
+
+/// Formats the description as JSON
+pub fn to_json(description: &Value) -> String {
+    let mut out = serde_json::to_string_pretty(description).unwrap();
+    out.push('\n');
+    out
+}
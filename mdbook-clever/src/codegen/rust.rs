@@ -0,0 +1,502 @@
+use std::{fmt::Write as _, io};
+
+use super::{camel_case, instruction_fields, opcode_variants};
+use crate::{
+    registry::{PropertyKind, Registry},
+    spec_lang::ast::BitRange,
+};
+
+pub struct RustGenerator<'a> {
+    registry: &'a Registry,
+    out: String,
+}
+
+impl<'a> RustGenerator<'a> {
+    pub fn new(registry: &'a Registry) -> Self {
+        Self {
+            registry,
+            out: String::new(),
+        }
+    }
+
+    pub fn generate(mut self) -> io::Result<String> {
+        let (opcode, control) = instruction_fields(self.registry)?;
+        self.write_all(opcode, control).map_err(io::Error::other)?;
+        Ok(self.out)
+    }
+
+    fn write_all(&mut self, opcode: BitRange, control: BitRange) -> core::fmt::Result {
+        writeln!(
+            self.out,
+            "// @generated by mdbook-clever from the Clever-ISA specification. Do not edit.\n"
+        )?;
+        self.write_extension()?;
+        self.write_register_property()?;
+        self.write_register()?;
+        self.write_opcode()?;
+        self.write_opcode_codec(opcode, control)
+    }
+
+    fn write_enum_head(&mut self, doc: &str, name: &str, repr: Option<&str>) -> core::fmt::Result {
+        writeln!(self.out, "/// {doc}")?;
+        writeln!(
+            self.out,
+            "#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]"
+        )?;
+        if let Some(repr) = repr {
+            writeln!(self.out, "#[repr({repr})]")?;
+        }
+        writeln!(self.out, "pub enum {name} {{")
+    }
+
+    fn write_extension(&mut self) -> core::fmt::Result {
+        let defs = &self.registry.extension_defs;
+
+        self.write_enum_head("An extension of the instruction set", "Extension", None)?;
+        for def in defs {
+            writeln!(self.out, "    /// `X-{}`", def.name)?;
+            writeln!(self.out, "    {},", camel_case(&def.name))?;
+        }
+        writeln!(self.out, "}}\n")?;
+
+        writeln!(self.out, "impl Extension {{")?;
+        writeln!(
+            self.out,
+            "    pub const ALL: [Extension; {}] = [",
+            defs.len()
+        )?;
+        for def in defs {
+            writeln!(self.out, "        Extension::{},", camel_case(&def.name))?;
+        }
+        writeln!(self.out, "    ];\n")?;
+
+        writeln!(
+            self.out,
+            "    /// The name of the extension, without the `X-` prefix"
+        )?;
+        writeln!(self.out, "    pub const fn name(self) -> &'static str {{")?;
+        writeln!(self.out, "        match self {{")?;
+        for def in defs {
+            writeln!(
+                self.out,
+                "            Extension::{} => {:?},",
+                camel_case(&def.name),
+                def.name
+            )?;
+        }
+        writeln!(self.out, "        }}")?;
+        writeln!(self.out, "    }}\n")?;
+
+        writeln!(self.out, "    /// The extensions this extension requires")?;
+        writeln!(
+            self.out,
+            "    pub const fn requires(self) -> &'static [Extension] {{"
+        )?;
+        writeln!(self.out, "        match self {{")?;
+        for def in defs {
+            let requires = def
+                .requires
+                .iter()
+                .map(|r| format!("Extension::{}", camel_case(r)))
+                .collect::<Vec<_>>()
+                .join(", ");
+            writeln!(
+                self.out,
+                "            Extension::{} => &[{requires}],",
+                camel_case(&def.name)
+            )?;
+        }
+        writeln!(self.out, "        }}")?;
+        writeln!(self.out, "    }}")?;
+        writeln!(self.out, "}}\n")
+    }
+
+    fn register_properties(&self) -> Vec<&'a str> {
+        let mut props = Vec::new();
+        for prop in &self.registry.properties {
+            if prop.kind == PropertyKind::Register && !props.contains(&&*prop.name) {
+                props.push(&*prop.name);
+            }
+        }
+        props
+    }
+
+    fn write_register_property(&mut self) -> core::fmt::Result {
+        let props = self.register_properties();
+
+        self.write_enum_head("A property of a register", "RegisterProperty", None)?;
+        for prop in &props {
+            writeln!(self.out, "    /// `{prop}`")?;
+            writeln!(self.out, "    {},", camel_case(prop))?;
+        }
+        writeln!(self.out, "}}\n")?;
+
+        writeln!(self.out, "impl RegisterProperty {{")?;
+        writeln!(self.out, "    pub const fn name(self) -> &'static str {{")?;
+        writeln!(self.out, "        match self {{")?;
+        for prop in &props {
+            writeln!(
+                self.out,
+                "            RegisterProperty::{} => {prop:?},",
+                camel_case(prop)
+            )?;
+        }
+        writeln!(self.out, "        }}")?;
+        writeln!(self.out, "    }}")?;
+        writeln!(self.out, "}}\n")
+    }
+
+    fn write_register(&mut self) -> core::fmt::Result {
+        let regs = &self.registry.registers;
+        let repr = if regs.iter().all(|r| r.number <= u8::MAX as u32) {
+            "u8"
+        } else {
+            "u16"
+        };
+        let props = self.register_properties();
+
+        self.write_enum_head(
+            "A register, with its register number",
+            "Register",
+            Some(repr),
+        )?;
+        for reg in regs {
+            writeln!(self.out, "    /// `{}`", reg.name)?;
+            writeln!(self.out, "    {} = {},", camel_case(&reg.name), reg.number)?;
+        }
+        writeln!(self.out, "}}\n")?;
+
+        writeln!(self.out, "impl Register {{")?;
+        writeln!(
+            self.out,
+            "    pub const ALL: [Register; {}] = [",
+            regs.len()
+        )?;
+        for reg in regs {
+            writeln!(self.out, "        Register::{},", camel_case(&reg.name))?;
+        }
+        writeln!(self.out, "    ];\n")?;
+
+        for reg in regs {
+            for alias in &reg.aliases {
+                writeln!(self.out, "    /// `{alias}`, an alias of `{}`", reg.name)?;
+                writeln!(
+                    self.out,
+                    "    pub const {}: Register = Register::{};\n",
+                    alias.to_ascii_uppercase(),
+                    camel_case(&reg.name)
+                )?;
+            }
+        }
+
+        writeln!(
+            self.out,
+            "    pub const fn from_number(number: {repr}) -> Option<Register> {{"
+        )?;
+        writeln!(self.out, "        match number {{")?;
+        for reg in regs {
+            writeln!(
+                self.out,
+                "            {} => Some(Register::{}),",
+                reg.number,
+                camel_case(&reg.name)
+            )?;
+        }
+        writeln!(self.out, "            _ => None,")?;
+        writeln!(self.out, "        }}")?;
+        writeln!(self.out, "    }}\n")?;
+
+        writeln!(self.out, "    pub const fn number(self) -> {repr} {{")?;
+        writeln!(self.out, "        self as {repr}")?;
+        writeln!(self.out, "    }}\n")?;
+
+        writeln!(self.out, "    pub const fn name(self) -> &'static str {{")?;
+        writeln!(self.out, "        match self {{")?;
+        for reg in regs {
+            writeln!(
+                self.out,
+                "            Register::{} => {:?},",
+                camel_case(&reg.name),
+                reg.name
+            )?;
+        }
+        writeln!(self.out, "        }}")?;
+        writeln!(self.out, "    }}\n")?;
+
+        writeln!(
+            self.out,
+            "    /// Looks up a register by its name or one of its aliases"
+        )?;
+        writeln!(
+            self.out,
+            "    pub fn from_name(name: &str) -> Option<Register> {{"
+        )?;
+        writeln!(self.out, "        match name {{")?;
+        for reg in regs {
+            let names = reg
+                .names()
+                .map(|n| format!("{n:?}"))
+                .collect::<Vec<_>>()
+                .join(" | ");
+            writeln!(
+                self.out,
+                "            {names} => Some(Register::{}),",
+                camel_case(&reg.name)
+            )?;
+        }
+        writeln!(self.out, "            _ => None,")?;
+        writeln!(self.out, "        }}")?;
+        writeln!(self.out, "    }}\n")?;
+
+        writeln!(
+            self.out,
+            "    pub const fn properties(self) -> &'static [RegisterProperty] {{"
+        )?;
+        writeln!(self.out, "        match self {{")?;
+        for reg in regs {
+            let list = reg
+                .properties
+                .iter()
+                .filter(|p| props.contains(&&***p))
+                .map(|p| format!("RegisterProperty::{}", camel_case(p)))
+                .collect::<Vec<_>>()
+                .join(", ");
+            writeln!(
+                self.out,
+                "            Register::{} => &[{list}],",
+                camel_case(&reg.name)
+            )?;
+        }
+        writeln!(self.out, "        }}")?;
+        writeln!(self.out, "    }}")?;
+        writeln!(self.out, "}}\n")
+    }
+
+    fn write_opcode(&mut self) -> core::fmt::Result {
+        let insns = &self.registry.instructions;
+        let variants = opcode_variants(self.registry);
+
+        self.write_enum_head("An instruction opcode", "Opcode", Some("u16"))?;
+        for (insn, variant) in insns.iter().zip(&variants) {
+            writeln!(self.out, "    /// `{}`", insn.mnemonic)?;
+            writeln!(self.out, "    {variant} = {:#06o},", insn.opcode)?;
+        }
+        writeln!(self.out, "}}\n")?;
+
+        writeln!(self.out, "impl Opcode {{")?;
+        writeln!(self.out, "    pub const ALL: [Opcode; {}] = [", insns.len())?;
+        for variant in &variants {
+            writeln!(self.out, "        Opcode::{variant},")?;
+        }
+        writeln!(self.out, "    ];\n")?;
+
+        writeln!(
+            self.out,
+            "    pub const fn from_u16(opcode: u16) -> Option<Opcode> {{"
+        )?;
+        writeln!(self.out, "        match opcode {{")?;
+        for (insn, variant) in insns.iter().zip(&variants) {
+            writeln!(
+                self.out,
+                "            {:#06o} => Some(Opcode::{variant}),",
+                insn.opcode
+            )?;
+        }
+        writeln!(self.out, "            _ => None,")?;
+        writeln!(self.out, "        }}")?;
+        writeln!(self.out, "    }}\n")?;
+
+        writeln!(
+            self.out,
+            "    pub const fn mnemonic(self) -> &'static str {{"
+        )?;
+        writeln!(self.out, "        match self {{")?;
+        for (insn, variant) in insns.iter().zip(&variants) {
+            writeln!(
+                self.out,
+                "            Opcode::{variant} => {:?},",
+                insn.mnemonic
+            )?;
+        }
+        writeln!(self.out, "        }}")?;
+        writeln!(self.out, "    }}\n")?;
+
+        writeln!(self.out, "    pub const fn extension(self) -> Extension {{")?;
+        writeln!(self.out, "        match self {{")?;
+        for (insn, variant) in insns.iter().zip(&variants) {
+            writeln!(
+                self.out,
+                "            Opcode::{variant} => Extension::{},",
+                camel_case(insn.extension_name())
+            )?;
+        }
+        writeln!(self.out, "        }}")?;
+        writeln!(self.out, "    }}\n")?;
+
+        writeln!(
+            self.out,
+            "    /// The number of operands the instruction takes, if the specification gives one"
+        )?;
+        writeln!(
+            self.out,
+            "    pub const fn operands(self) -> Option<u32> {{"
+        )?;
+        writeln!(self.out, "        match self {{")?;
+        for (insn, variant) in insns.iter().zip(&variants) {
+            match insn.operands {
+                Some(count) => {
+                    writeln!(self.out, "            Opcode::{variant} => Some({count}),")?
+                }
+                None => writeln!(self.out, "            Opcode::{variant} => None,")?,
+            }
+        }
+        writeln!(self.out, "        }}")?;
+        writeln!(self.out, "    }}")
+    }
+
+    /// Writes `encode` and `decode`, which convert between an opcode with its control bits and the first word of an
+    /// instruction, according to the instruction encoding
+    fn write_opcode_codec(&mut self, opcode: BitRange, control: BitRange) -> core::fmt::Result {
+        let mask = |range: BitRange| (1u32 << range.width()) - 1;
+        // A shift by 0 is left out, as it would trip clippy's `identity_op` lint in the generated code
+        let shift = |expr: &str, op: &str, amount: u32| match amount {
+            0 => expr.to_string(),
+            amount => format!("({expr} {op} {amount})"),
+        };
+
+        writeln!(self.out)?;
+        writeln!(
+            self.out,
+            "    /// Encodes the first word of an instruction with this opcode and the control bits `h`"
+        )?;
+        writeln!(self.out, "    pub const fn encode(self, h: u8) -> u16 {{")?;
+        writeln!(
+            self.out,
+            "        {} | {}",
+            shift("(self as u16)", "<<", opcode.lo),
+            shift(
+                &format!("((h as u16) & {:#x})", mask(control)),
+                "<<",
+                control.lo
+            )
+        )?;
+        writeln!(self.out, "    }}\n")?;
+
+        writeln!(
+            self.out,
+            "    /// Decodes the first word of an instruction into its opcode and control bits"
+        )?;
+        writeln!(
+            self.out,
+            "    pub const fn decode(word: u16) -> Option<(Opcode, u8)> {{"
+        )?;
+        writeln!(
+            self.out,
+            "        let h = ({} & {:#x}) as u8;",
+            shift("word", ">>", control.lo),
+            mask(control)
+        )?;
+        writeln!(
+            self.out,
+            "        match Opcode::from_u16({} & {:#o}) {{",
+            shift("word", ">>", opcode.lo),
+            mask(opcode)
+        )?;
+        writeln!(self.out, "            Some(opcode) => Some((opcode, h)),")?;
+        writeln!(self.out, "            None => None,")?;
+        writeln!(self.out, "        }}")?;
+        writeln!(self.out, "    }}")?;
+        writeln!(self.out, "}}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, process::Command};
+
+    use super::*;
+    use crate::registry::{EncodingDef, ExtensionDef, Instruction, RegisterDef};
+
+    fn registry(fields: [(&str, BitRange); 2]) -> Registry {
+        let instruction = |opcode: u16, mnemonic: &str| Instruction {
+            opcode,
+            mnemonic: mnemonic.to_string(),
+            aliases: Vec::new(),
+            extension: None,
+            operands: Some(0),
+            control: None,
+            op_group: None,
+            properties: Vec::new(),
+            operand_properties: Vec::new(),
+            chapter: PathBuf::from("extensions/main.md"),
+            anchor: None,
+        };
+
+        let mut registry = Registry::default();
+        registry.instructions = vec![instruction(0o0000, "und"), instruction(0o7777, "und")];
+        registry.extension_defs = vec![ExtensionDef {
+            name: "main".to_string(),
+            version: None,
+            requires: Vec::new(),
+            chapter: PathBuf::from("extensions/main.md"),
+        }];
+        // The generated `Register` enum has a `repr`, so it needs a variant
+        registry.registers = vec![RegisterDef {
+            name: "r0".to_string(),
+            number: 0,
+            aliases: Vec::new(),
+            properties: Vec::new(),
+            fields: Vec::new(),
+            unspecified_fields: Vec::new(),
+            chapter: PathBuf::from("extensions/main.md"),
+        }];
+        registry.encodings = vec![EncodingDef {
+            label: "E:insn".to_string(),
+            width: 16,
+            fields: fields
+                .map(|(name, range)| (name.to_string(), range))
+                .to_vec(),
+            fixed: Vec::new(),
+            chapter: PathBuf::from("extensions/main.md"),
+        }];
+        registry
+    }
+
+    /// Compiles the generated module with a `main` that checks that `decode` inverts `encode`, and runs it
+    fn assert_round_trip(registry: &Registry, name: &str) {
+        let source = RustGenerator::new(registry).generate().unwrap();
+        assert!(!source.contains("<< 0") && !source.contains(">> 0"));
+
+        let dir = std::env::temp_dir().join(format!("mdbook-clever-codec-{name}"));
+        std::fs::create_dir_all(&dir).unwrap();
+        let main = dir.join("main.rs");
+        std::fs::write(
+            &main,
+            format!(
+                "#![allow(dead_code)]\n{source}\nfn main() {{\n    for opcode in Opcode::ALL {{\n        for h in 0..16 {{\n            assert_eq!(Opcode::decode(opcode.encode(h)), Some((opcode, h)));\n        }}\n    }}\n}}\n"
+            ),
+        )
+        .unwrap();
+
+        let exe = dir.join("codec");
+        let status = Command::new(std::env::var("RUSTC").unwrap_or_else(|_| "rustc".into()))
+            .args(["--edition", "2021", "-o"])
+            .arg(&exe)
+            .arg(&main)
+            .status()
+            .unwrap();
+        assert!(status.success());
+        assert!(Command::new(&exe).status().unwrap().success());
+    }
+
+    #[test]
+    fn opcode_codec_round_trips() {
+        let opcode = BitRange { hi: 15, lo: 4 };
+        let h = BitRange { hi: 3, lo: 0 };
+        assert_round_trip(&registry([("opcode", opcode), ("h", h)]), "low-h");
+
+        let opcode = BitRange { hi: 11, lo: 0 };
+        let h = BitRange { hi: 15, lo: 12 };
+        assert_round_trip(&registry([("opcode", opcode), ("h", h)]), "high-h");
+    }
+}
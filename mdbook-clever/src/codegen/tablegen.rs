@@ -0,0 +1,173 @@
+use std::{fmt::Write as _, io};
+
+use super::{instruction_fields, opcode_variants};
+use crate::registry::{RegGroup, Registry};
+
+/// The name of the TableGen record for a register, such as `R0` for `r0`
+fn register_record(name: &str) -> String {
+    name.to_ascii_uppercase()
+}
+
+/// The value types of a register group, such as `i32` for a 32-bit `Integer`
+fn value_types(group: &RegGroup) -> Vec<String> {
+    group
+        .types
+        .iter()
+        .flat_map(|(kind, widths)| {
+            widths.iter().filter_map(move |&width| match &**kind {
+                "Integer" => Some(format!("i{width}")),
+                "Float" => Some(format!("f{width}")),
+                "Vector" => Some(format!("v{}i8", width / 8)),
+                _ => None,
+            })
+        })
+        .collect()
+}
+
+pub struct TableGenGenerator<'a> {
+    registry: &'a Registry,
+    out: String,
+}
+
+impl<'a> TableGenGenerator<'a> {
+    pub fn new(registry: &'a Registry) -> Self {
+        Self {
+            registry,
+            out: String::new(),
+        }
+    }
+
+    pub fn generate(mut self) -> io::Result<String> {
+        let (opcode, _) = instruction_fields(self.registry)?;
+        self.write_all(opcode.width()).map_err(io::Error::other)?;
+        Ok(self.out)
+    }
+
+    fn write_all(&mut self, opcode_width: u32) -> core::fmt::Result {
+        writeln!(
+            self.out,
+            "// @generated by mdbook-clever from the Clever-ISA specification. Do not edit.\n"
+        )?;
+        self.write_registers()?;
+        self.write_register_classes()?;
+        self.write_instructions(opcode_width)
+    }
+
+    /// The registers named by the specification or by a register group, in order of register number and then of
+    /// first appearance in a group
+    fn register_names(&self) -> Vec<&'a str> {
+        let mut names: Vec<&str> = self.registry.registers.iter().map(|r| &*r.name).collect();
+        for group in &self.registry.reg_groups {
+            for reg in &group.registers {
+                if !names.contains(&&**reg) {
+                    names.push(reg);
+                }
+            }
+        }
+        names
+    }
+
+    fn write_registers(&mut self) -> core::fmt::Result {
+        writeln!(
+            self.out,
+            "class CleverReg<string n, list<string> altNames = []> : Register<n, altNames> {{"
+        )?;
+        writeln!(self.out, "  let Namespace = \"Clever\";")?;
+        writeln!(self.out, "}}\n")?;
+
+        for name in self.register_names() {
+            let record = register_record(name);
+            match self.registry.register(name) {
+                Some(reg) => {
+                    let aliases = reg
+                        .aliases
+                        .iter()
+                        .map(|a| format!("{a:?}"))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    writeln!(
+                        self.out,
+                        "let HWEncoding = {} in def {record} : CleverReg<{name:?}, [{aliases}]>;",
+                        reg.number
+                    )?;
+                }
+                None => writeln!(
+                    self.out,
+                    "def {record} : CleverReg<{name:?}>; // Not yet numbered by the specification"
+                )?,
+            }
+        }
+        writeln!(self.out)
+    }
+
+    fn write_register_classes(&mut self) -> core::fmt::Result {
+        for group in &self.registry.reg_groups {
+            let types = value_types(group);
+            let align = group
+                .types
+                .iter()
+                .flat_map(|(_, widths)| widths)
+                .max()
+                .copied()
+                .unwrap_or(64);
+            let registers = group
+                .registers
+                .iter()
+                .map(|r| register_record(r))
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            writeln!(
+                self.out,
+                "// Constraint code \"{}\", requires the `{}` feature",
+                group.constraint, group.feature
+            )?;
+            let modifiers = self
+                .registry
+                .asm_modifiers
+                .iter()
+                .filter(|m| m.groups.contains(&group.name))
+                .map(|m| &*m.name)
+                .collect::<Vec<_>>();
+            if !modifiers.is_empty() {
+                writeln!(self.out, "// Operand modifiers: {}", modifiers.join(", "))?;
+            }
+            writeln!(
+                self.out,
+                "def {} : RegisterClass<\"Clever\", [{}], {align}, (add {registers})>;\n",
+                group.name.to_ascii_uppercase(),
+                types.join(", ")
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn write_instructions(&mut self, opcode_width: u32) -> core::fmt::Result {
+        writeln!(
+            self.out,
+            "class CleverInst<bits<{opcode_width}> opcode, string mnemonic> : Instruction {{"
+        )?;
+        writeln!(self.out, "  let Namespace = \"Clever\";")?;
+        writeln!(self.out, "  field bits<{opcode_width}> Opcode = opcode;")?;
+        writeln!(self.out, "  let OutOperandList = (outs);")?;
+        writeln!(self.out, "  let InOperandList = (ins);")?;
+        writeln!(self.out, "  let AsmString = mnemonic;")?;
+        writeln!(self.out, "}}\n")?;
+
+        let variants = opcode_variants(self.registry);
+        for (insn, variant) in self.registry.instructions.iter().zip(&variants) {
+            writeln!(
+                self.out,
+                "def {} : CleverInst<{:#x}, {:?}>; // {:#06o}, X-{}",
+                variant.to_ascii_uppercase(),
+                insn.opcode,
+                insn.mnemonic,
+                insn.opcode,
+                insn.extension_name()
+            )?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,269 @@
+//! The configuration of the preprocessor, read from the `[preprocessor.clever-spec]` table of `book.toml`.
+//!
+//! ```toml
+//! [preprocessor.clever-spec]
+//! renderers = ["html", "markdown"]
+//! info-strings = ["clever-spec"]
+//! isa-description = "clever-isa.json"
+//! table-numbering = "chapter"
+//!
+//! [preprocessor.clever-spec.tag-groups]
+//! D = "documents"
+//!
+//! [preprocessor.clever-spec.checks]
+//! property-uses = "error"
+//! ```
+
+use std::{collections::BTreeMap, io, path::PathBuf};
+
+use serde_json::Value;
+
+use crate::spec_lang::BlockInfo;
+
+/// The key of the preprocessor's table in `book.toml`
+pub const PREPROCESSOR_NAME: &str = "clever-spec";
+
+/// The keys of the preprocessor's table that are read by mdbook rather than by the preprocessor
+const MDBOOK_KEYS: [&str; 3] = ["command", "before", "after"];
+
+/// The tag groups used when `tag-groups` does not override them, from the prefix of a tag such as `X-float` to the
+/// directory holding its chapter
+const DEFAULT_TAG_GROUPS: [(&str, &str); 3] =
+    [("D", "documents"), ("X", "extensions"), ("R", "reports")];
+
+/// The language of the info string of code blocks that are written in the specification language
+const DEFAULT_SPEC_LANG: &str = "clever-spec";
+
+fn config_error(msg: impl core::fmt::Display) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("[preprocessor.{PREPROCESSOR_NAME}]: {msg}"),
+    )
+}
+
+/// How a failed check is reported
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Level {
+    /// Fails the build
+    Error,
+    /// Prints a warning and continues
+    Warn,
+    /// Skips the check
+    Allow,
+}
+
+impl Level {
+    fn parse(key: &str, value: &Value) -> io::Result<Self> {
+        match value.as_str() {
+            Some("error") => Ok(Level::Error),
+            Some("warn") => Ok(Level::Warn),
+            Some("allow") => Ok(Level::Allow),
+            _ => Err(config_error(format!(
+                "`checks.{key}` must be one of \"error\", \"warn\" or \"allow\", but is {value}"
+            ))),
+        }
+    }
+
+    /// Reports the outcome of a check at this level, passing on the error only at [`Level::Error`]
+    pub fn report(self, result: io::Result<()>) -> io::Result<()> {
+        match (self, result) {
+            (Level::Error, result) => result,
+            (Level::Warn, Err(e)) => {
+                eprintln!("Warning: {e}");
+                Ok(())
+            }
+            (Level::Warn | Level::Allow, _) => Ok(()),
+        }
+    }
+}
+
+/// How captioned tables are numbered, from `table-numbering`
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum TableNumbering {
+    /// Within each numbered chapter, such as `Table 3.2` for the second table of chapter 3
+    #[default]
+    Chapter,
+    /// Through the whole book, such as `Table 12`
+    Book,
+}
+
+impl TableNumbering {
+    fn parse(value: &Value) -> io::Result<Self> {
+        match value.as_str() {
+            Some("chapter") => Ok(TableNumbering::Chapter),
+            Some("book") => Ok(TableNumbering::Book),
+            _ => Err(config_error(format!(
+                "`table-numbering` must be one of \"chapter\" or \"book\", but is {value}"
+            ))),
+        }
+    }
+}
+
+/// The level of each check made while building the book, from the `checks` table
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Checks {
+    /// That extension prerequisites exist, are not newer than the extension, and do not form a cycle
+    pub extensions: Level,
+    /// That each property used by an instruction, operand or register is defined
+    pub property_uses: Level,
+    /// That exceptions are unique, and that each exception raised or listed is defined
+    pub exceptions: Level,
+    /// That each instruction's `Op Group` names an operand table matching its operands
+    pub op_groups: Level,
+    /// That each instruction's `Control` pattern matches the control field and its pseudocode
+    pub control_bits: Level,
+    /// That `clever-asm` examples use defined instructions and registers, and can be encoded
+    pub asm: Level,
+    /// That the items of `clever-spec` blocks are well-formed, such as tables having rows of equal width
+    pub spec_blocks: Level,
+}
+
+impl Default for Checks {
+    fn default() -> Self {
+        Self {
+            extensions: Level::Error,
+            property_uses: Level::Warn,
+            exceptions: Level::Error,
+            op_groups: Level::Error,
+            control_bits: Level::Error,
+            asm: Level::Error,
+            spec_blocks: Level::Error,
+        }
+    }
+}
+
+impl Checks {
+    fn level_mut(&mut self, key: &str) -> Option<&mut Level> {
+        match key {
+            "extensions" => Some(&mut self.extensions),
+            "property-uses" => Some(&mut self.property_uses),
+            "exceptions" => Some(&mut self.exceptions),
+            "op-groups" => Some(&mut self.op_groups),
+            "control-bits" => Some(&mut self.control_bits),
+            "asm" => Some(&mut self.asm),
+            "spec-blocks" => Some(&mut self.spec_blocks),
+            _ => None,
+        }
+    }
+}
+
+/// The typed contents of the `[preprocessor.clever-spec]` table
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Config {
+    /// The renderers to run for, out of those the preprocessor supports, or all of them if not set
+    pub renderers: Option<Vec<String>>,
+    /// The directory, relative to the book's `src` directory, of the chapters named by each tag prefix, such as
+    /// `extensions` for `X` in `X-float`
+    pub tag_groups: BTreeMap<String, PathBuf>,
+    pub checks: Checks,
+    /// The languages, such as `clever-spec`, of the info strings of code blocks written in the specification language
+    pub info_strings: Vec<String>,
+    /// Where to write the description of the instruction set, relative to the build directory
+    pub isa_description: Option<PathBuf>,
+    pub table_numbering: TableNumbering,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            renderers: None,
+            tag_groups: DEFAULT_TAG_GROUPS
+                .iter()
+                .map(|&(prefix, dir)| (prefix.to_string(), PathBuf::from(dir)))
+                .collect(),
+            checks: Checks::default(),
+            info_strings: vec![DEFAULT_SPEC_LANG.to_string()],
+            isa_description: None,
+            table_numbering: TableNumbering::default(),
+        }
+    }
+}
+
+fn string_list(key: &str, value: &Value) -> io::Result<Vec<String>> {
+    value
+        .as_array()
+        .and_then(|values| {
+            values
+                .iter()
+                .map(|v| v.as_str().map(str::to_string))
+                .collect()
+        })
+        .ok_or_else(|| config_error(format!("`{key}` must be an array of strings")))
+}
+
+fn table<'a>(key: &str, value: &'a Value) -> io::Result<&'a serde_json::Map<String, Value>> {
+    value
+        .as_object()
+        .ok_or_else(|| config_error(format!("`{key}` must be a table")))
+}
+
+impl Config {
+    /// Reads the preprocessor's table from the book's configuration, which is the default configuration if the table
+    /// is absent. Unknown keys are an error, so that misspelt settings are not silently ignored.
+    pub fn load(config: &mdbook::Config) -> io::Result<Self> {
+        let mut this = Self::default();
+
+        let Some(value) = config.get(&format!("preprocessor.{PREPROCESSOR_NAME}")) else {
+            return Ok(this);
+        };
+        let value = serde_json::to_value(value).map_err(config_error)?;
+
+        for (key, value) in table(PREPROCESSOR_NAME, &value)? {
+            match &**key {
+                key if MDBOOK_KEYS.contains(&key) => {}
+                "renderers" => this.renderers = Some(string_list(key, value)?),
+                "info-strings" => this.info_strings = string_list(key, value)?,
+                "isa-description" => {
+                    let path = value.as_str().ok_or_else(|| {
+                        config_error(
+                            "`isa-description` must be a path, such as \"clever-isa.json\"",
+                        )
+                    })?;
+                    this.isa_description = Some(PathBuf::from(path));
+                }
+                "table-numbering" => this.table_numbering = TableNumbering::parse(value)?,
+                "tag-groups" => {
+                    for (prefix, dir) in table(key, value)? {
+                        let dir = dir.as_str().ok_or_else(|| {
+                            config_error(format!("`tag-groups.{prefix}` must be a directory"))
+                        })?;
+                        this.tag_groups.insert(prefix.clone(), PathBuf::from(dir));
+                    }
+                }
+                "checks" => {
+                    for (check, level) in table(key, value)? {
+                        let slot = this.checks.level_mut(check).ok_or_else(|| {
+                            config_error(format!("Unknown check `checks.{check}`"))
+                        })?;
+                        *slot = Level::parse(check, level)?;
+                    }
+                }
+                key => return Err(config_error(format!("Unknown key `{key}`"))),
+            }
+        }
+
+        Ok(this)
+    }
+
+    /// Whether the preprocessor should run for `renderer`, which must be one of `supported` and, if `renderers` is
+    /// set, one of those
+    pub fn runs_for(&self, renderer: &str, supported: &[&str]) -> bool {
+        supported.contains(&renderer)
+            && self
+                .renderers
+                .as_ref()
+                .is_none_or(|list| list.iter().any(|r| r == renderer))
+    }
+
+    /// How a code block with the info string `lang` is treated, if it is written in the specification language, or an
+    /// error if its options are invalid
+    pub fn spec_block<'a>(&self, lang: &'a str) -> Option<Result<BlockInfo<'a>, String>> {
+        let mut parts = lang.trim().split(',');
+        let lang = parts.next()?.trim();
+
+        self.info_strings
+            .iter()
+            .any(|s| s == lang)
+            .then(|| BlockInfo::from_options(parts))
+    }
+}
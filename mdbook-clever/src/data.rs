@@ -0,0 +1,326 @@
+use std::{borrow::Cow, io, path::Path};
+
+use crate::spec_lang::{
+    Spanned,
+    ast::{Array, DataSource, Elem, Item, Table},
+};
+
+/// A table of string values read from a data file, with the column names from its header
+#[derive(Debug)]
+struct DataTable {
+    columns: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+fn invalid_data(path: &Path, msg: impl core::fmt::Display) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{}: {msg}", path.display()),
+    )
+}
+
+/// Loads the rows of every table in `item` that is declared with `from "<path>"`.
+///
+/// Paths are resolved relative to `dir`, the directory containing the chapter's source file.
+pub fn load_item(item: &mut Item, dir: &Path) -> io::Result<()> {
+    match item {
+        Item::Elem(elem) => load_elem(elem, dir),
+        Item::Encoding(_) | Item::Register(_) | Item::Exception(_) => Ok(()),
+    }
+}
+
+fn load_elem(elem: &mut Elem, dir: &Path) -> io::Result<()> {
+    match elem {
+        Elem::Table(table) => load_table(table, dir),
+        Elem::MarkdownLiteral(_) | Elem::StringLiteral(_) => Ok(()),
+    }
+}
+
+fn load_table(table: &mut Table, dir: &Path) -> io::Result<()> {
+    for row in &mut table.rows {
+        for cell in &mut row.body.0 {
+            load_elem(&mut cell.body, dir)?;
+        }
+    }
+
+    let Some(source) = &table.source else {
+        return Ok(());
+    };
+
+    let path = dir.join(&*source.body.path.body);
+    let data = read_data(&path, &source.body)?;
+
+    let selected = match &source.body.columns {
+        Some(columns) => columns
+            .body
+            .iter()
+            .map(|col| {
+                data.columns
+                    .iter()
+                    .position(|c| *c == col.body)
+                    .ok_or_else(|| invalid_data(&path, format!("No column named `{}`", col.body)))
+            })
+            .collect::<io::Result<Vec<_>>>()?,
+        None => (0..data.columns.len()).collect(),
+    };
+
+    let filters = source
+        .body
+        .filters
+        .iter()
+        .map(|filter| {
+            data.columns
+                .iter()
+                .position(|c| *c == filter.body.column.body)
+                .map(|idx| (idx, &filter.body))
+                .ok_or_else(|| {
+                    invalid_data(
+                        &path,
+                        format!("No column named `{}`", filter.body.column.body),
+                    )
+                })
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+
+    let span = source.span.clone();
+    let cell = |value: &str| Spanned {
+        body: Elem::StringLiteral(Cow::Owned(value.to_string())),
+        span: span.clone(),
+    };
+
+    match &table.heading {
+        Some(heading) if heading.body.0.len() != selected.len() => {
+            return Err(invalid_data(
+                &path,
+                format!(
+                    "Table heading has {} columns, but {} columns were selected",
+                    heading.body.0.len(),
+                    selected.len()
+                ),
+            ));
+        }
+        Some(_) => {}
+        None => {
+            table.heading = Some(Spanned {
+                body: Array(
+                    selected
+                        .iter()
+                        .map(|&idx| cell(&data.columns[idx]))
+                        .collect(),
+                ),
+                span: span.clone(),
+            })
+        }
+    }
+
+    for row in &data.rows {
+        let value = |idx: usize| row.get(idx).map_or("", |v| &**v);
+
+        if filters
+            .iter()
+            .all(|(idx, filter)| filter.matches(value(*idx)))
+        {
+            table.rows.push(Spanned {
+                body: Array(selected.iter().map(|&idx| cell(value(idx))).collect()),
+                span: span.clone(),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+fn read_data(path: &Path, source: &DataSource) -> io::Result<DataTable> {
+    let text = std::fs::read_to_string(path).map_err(|e| invalid_data(path, e))?;
+
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("csv") => read_csv(&text).map_err(|msg| invalid_data(path, msg)),
+        Some("toml") => read_toml(path, &text, source.key.as_ref().map(|k| k.body.0)),
+        _ => Err(invalid_data(
+            path,
+            "Unsupported data file, expected a `.csv` or `.toml` file",
+        )),
+    }
+}
+
+/// Reads comma separated values, where the first record is the header.
+///
+/// Fields may be quoted with `"`, in which case they may contain commas, newlines, and `""` for a literal quote.
+/// Every record must have as many fields as the header.
+fn read_csv(text: &str) -> Result<DataTable, String> {
+    // Each record with the line it starts on
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut line = 1;
+    let mut start = 1;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c == '\n' {
+            line += 1;
+        }
+        match c {
+            '"' if quoted => {
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    field.push('"');
+                } else {
+                    quoted = false;
+                }
+            }
+            '"' if field.is_empty() => quoted = true,
+            ',' if !quoted => record.push(core::mem::take(&mut field)),
+            '\n' if !quoted => {
+                record.push(core::mem::take(&mut field));
+                records.push((start, core::mem::take(&mut record)));
+                start = line;
+            }
+            '\r' if !quoted && chars.peek() == Some(&'\n') => {}
+            c => field.push(c),
+        }
+    }
+
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push((start, record));
+    }
+
+    records.retain(|(_, r)| !matches!(&r[..], [f] if f.is_empty()));
+
+    let mut records = records.into_iter();
+    let columns = records.next().map(|(_, r)| r).unwrap_or_default();
+    let rows = records
+        .enumerate()
+        .map(|(idx, (line, record))| {
+            if record.len() == columns.len() {
+                Ok(record)
+            } else {
+                Err(format!(
+                    "Record {} on line {line} has {} fields, but the header has {}",
+                    idx + 1,
+                    record.len(),
+                    columns.len()
+                ))
+            }
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok(DataTable { columns, rows })
+}
+
+fn toml_to_string(value: &toml::Value) -> String {
+    match value {
+        toml::Value::String(st) => st.clone(),
+        toml::Value::Array(values) => values
+            .iter()
+            .map(toml_to_string)
+            .collect::<Vec<_>>()
+            .join(", "),
+        value => value.to_string(),
+    }
+}
+
+/// Looks up a dotted key, such as `version.contents`
+fn toml_get<'a>(doc: &'a toml::Table, key: &str) -> Option<&'a toml::Value> {
+    let (table, last) = match key.rsplit_once('.') {
+        Some((parent, last)) => (
+            parent
+                .split('.')
+                .try_fold(doc, |table, key| table.get(key)?.as_table())?,
+            last,
+        ),
+        None => (doc, key),
+    };
+
+    table.get(last)
+}
+
+/// Reads the rows of an array of tables, either the one named by `key` or the first one in the document.
+///
+/// The columns are each key that appears in any of the tables, in order of first appearance.
+fn read_toml(path: &Path, text: &str, key: Option<&str>) -> io::Result<DataTable> {
+    let doc = text
+        .parse::<toml::Table>()
+        .map_err(|e| invalid_data(path, e))?;
+
+    let rows = match key {
+        Some(key) => toml_get(&doc, key).and_then(toml::Value::as_array),
+        None => doc.values().find_map(|v| {
+            v.as_array()
+                .filter(|arr| arr.iter().all(toml::Value::is_table))
+        }),
+    }
+    .ok_or_else(|| {
+        invalid_data(
+            path,
+            match key {
+                Some(key) => format!("`{key}` is not an array of tables"),
+                None => "Expected an array of tables".to_string(),
+            },
+        )
+    })?;
+
+    let mut columns: Vec<String> = Vec::new();
+    let tables = rows
+        .iter()
+        .map(|row| {
+            row.as_table()
+                .ok_or_else(|| invalid_data(path, "Expected an array of tables"))
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+
+    for table in &tables {
+        for key in table.keys() {
+            if !columns.contains(key) {
+                columns.push(key.clone());
+            }
+        }
+    }
+
+    let rows = tables
+        .iter()
+        .map(|table| {
+            columns
+                .iter()
+                .map(|col| table.get(col).map(toml_to_string).unwrap_or_default())
+                .collect()
+        })
+        .collect();
+
+    Ok(DataTable { columns, rows })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_csv_handles_quotes_and_crlf() {
+        let data = read_csv("a,b\r\n\"x, y\",\"say \"\"hi\"\"\"\r\n\"multi\nline\",z\r\n").unwrap();
+        assert_eq!(data.columns, ["a", "b"]);
+        assert_eq!(
+            data.rows,
+            [["x, y", "say \"hi\""], ["multi\nline", "z"]].map(|r| r.map(String::from).to_vec())
+        );
+    }
+
+    #[test]
+    fn read_csv_skips_blank_lines_and_reads_a_final_record_without_newline() {
+        let data = read_csv("a,b\n\n1,2\n3,4").unwrap();
+        assert_eq!(
+            data.rows,
+            [["1", "2"], ["3", "4"]].map(|r| r.map(String::from).to_vec())
+        );
+    }
+
+    #[test]
+    fn read_csv_rejects_ragged_records() {
+        let err = read_csv("a,b,c\n1,2,3\n\"4\n\",5\n").unwrap_err();
+        assert_eq!(err, "Record 2 on line 3 has 2 fields, but the header has 3");
+
+        let err = read_csv("a,b\n1,2,3\n").unwrap_err();
+        assert_eq!(err, "Record 1 on line 2 has 3 fields, but the header has 2");
+    }
+}
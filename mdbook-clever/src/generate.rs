@@ -0,0 +1,672 @@
+use std::{borrow::Cow, io, ops::Range};
+
+use mdbook_fiction_tools::xhtml::{write_rich_node, xml_to_io_error};
+use xml::{EventWriter, writer::XmlEvent};
+
+use crate::{
+    helpers::{self, TagExpander},
+    registry::Registry,
+    render::{
+        Backend,
+        markdown::{code, inline_markdown, write_pipe_table},
+    },
+    spec_lang::ast::{Elem, Exception},
+};
+
+const DIRECTIVE_START: &str = "{{#clever-";
+const DIRECTIVE_END: &str = "}}";
+
+/// Background colours assigned to extensions in generated diagrams, in order of first appearance
+const PALETTE: &[&str] = &[
+    "#8dd3c7", "#ffffb3", "#bebada", "#fb8072", "#80b1d3", "#fdb462", "#b3de69", "#fccde5",
+    "#d9d9d9", "#bc80bd", "#ccebc5", "#ffed6f",
+];
+
+/// Finds the first `{{#clever-<name>}}` directive in `text`, giving the range of the whole directive and `<name>`
+pub fn find_directive(text: &str) -> Option<io::Result<(Range<usize>, &str)>> {
+    let start = text.find(DIRECTIVE_START)?;
+    let directive = &text[start + DIRECTIVE_START.len()..];
+    let Some(end) = directive.find(DIRECTIVE_END) else {
+        return Some(Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Unterminated directive",
+        )));
+    };
+    let range = start..start + DIRECTIVE_START.len() + end + DIRECTIVE_END.len();
+
+    Some(Ok((range, directive[..end].trim())))
+}
+
+fn unknown_directive(directive: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Unknown directive `{{{{#clever-{directive}}}}}`"),
+    )
+}
+
+/// A markdown link to `href`
+fn link(text: &str, href: &str) -> String {
+    format!("[{text}]({href})")
+}
+
+pub struct Generator<'a> {
+    registry: &'a Registry,
+    tag: TagExpander<'a>,
+    /// The backend for the renderer the book is being built for, which chooses how directives are written
+    backend: &'a dyn Backend,
+}
+
+impl<'a> Generator<'a> {
+    pub const fn new(
+        registry: &'a Registry,
+        tag: TagExpander<'a>,
+        backend: &'a dyn Backend,
+    ) -> Self {
+        Self {
+            registry,
+            tag,
+            backend,
+        }
+    }
+
+    /// Writes the generated content for the directive `{{#clever-<directive>}}` to `output`
+    pub fn expand_directive(&self, directive: &str, output: &mut String) -> io::Result<()> {
+        self.backend.write_directive(self, directive, output)
+    }
+
+    /// Writes the generated content for a directive as XHTML
+    pub fn expand_directive_xhtml(&self, directive: &str, output: &mut String) -> io::Result<()> {
+        let mut writer = helpers::StringAppender(output);
+        let mut writer = helpers::xml_writer(&mut writer);
+        match directive {
+            "opcode-map" => self.write_opcode_map(&mut writer).map_err(xml_to_io_error),
+            "exceptions" => self
+                .write_exception_summary(&mut writer)
+                .map_err(xml_to_io_error),
+            "extension-graph" => self
+                .write_extension_graph(&mut writer)
+                .map_err(xml_to_io_error),
+            "list-of-tables" => self
+                .write_list_of_tables(&mut writer)
+                .map_err(xml_to_io_error),
+            d => Err(unknown_directive(d)),
+        }
+    }
+
+    /// Writes the generated content for a directive as pipe tables, for the `markdown` renderer. Diagrams such as the
+    /// extension graph have no markdown form, so only the tables that accompany them are written.
+    pub fn expand_directive_markdown(&self, directive: &str, out: &mut String) -> io::Result<()> {
+        match directive {
+            "opcode-map" => self.write_opcode_map_markdown(out),
+            "exceptions" => self.write_exception_summary_markdown(out),
+            "extension-graph" => self.write_extension_table_markdown(out),
+            "list-of-tables" => self.write_list_of_tables_markdown(out),
+            d => return Err(unknown_directive(d)),
+        }
+        Ok(())
+    }
+
+    fn extension_link(&self, ext: &str) -> String {
+        let href = self
+            .tag
+            .chapter_link(format!("extensions/{ext}.md").as_ref(), None);
+        link(&format!("X-{ext}"), &href)
+    }
+
+    /// Writes the assigned opcodes in order, as the grid of the opcode map is too wide for a pipe table, followed by
+    /// the same legend and free ranges as [`Self::write_opcode_map`]
+    fn write_opcode_map_markdown(&self, out: &mut String) {
+        let mut insns = self.registry.instructions.iter().collect::<Vec<_>>();
+        insns.sort_by_key(|insn| insn.opcode);
+        let rows = insns
+            .iter()
+            .map(|insn| {
+                let href = self.tag.chapter_link(&insn.chapter, insn.anchor.as_deref());
+                vec![
+                    code(&format!("{:#06o}", insn.opcode)),
+                    link(&insn.mnemonic, &href),
+                    self.extension_link(insn.extension_name()),
+                ]
+            })
+            .collect::<Vec<_>>();
+        write_pipe_table(
+            out,
+            &["Opcode", "Mnemonic", "Extension"].map(String::from),
+            &rows,
+        );
+        out.push('\n');
+
+        let rows = self
+            .registry
+            .extensions()
+            .iter()
+            .map(|&ext| {
+                let count = self
+                    .registry
+                    .instructions
+                    .iter()
+                    .filter(|i| i.extension_name() == ext)
+                    .count();
+                vec![self.extension_link(ext), count.to_string()]
+            })
+            .collect::<Vec<_>>();
+        write_pipe_table(out, &["Extension", "Opcodes"].map(String::from), &rows);
+        out.push('\n');
+
+        let rows = self
+            .registry
+            .free_ranges()
+            .into_iter()
+            .map(|(start, end)| {
+                let range = if start == end {
+                    format!("{start:#06o}")
+                } else {
+                    format!("{start:#06o}\u{2013}{end:#06o}")
+                };
+                vec![code(&range), (end - start + 1).to_string()]
+            })
+            .collect::<Vec<_>>();
+        write_pipe_table(out, &["Free Opcodes", "Count"].map(String::from), &rows);
+    }
+
+    fn write_exception_summary_markdown(&self, out: &mut String) {
+        let rows = self
+            .registry
+            .exceptions
+            .iter()
+            .map(|exception| {
+                let href = self
+                    .tag
+                    .chapter_link(&exception.chapter, Some(&exception.anchor()));
+                let markdown = |text: &Option<String>| {
+                    text.as_deref().map(inline_markdown).unwrap_or_default()
+                };
+                vec![
+                    exception.vector.map_or_else(
+                        || "*unassigned*".to_string(),
+                        |vector| code(&vector.to_string()),
+                    ),
+                    link(&exception.name, &href),
+                    exception
+                        .code
+                        .as_deref()
+                        .map_or_else(|| "*unassigned*".to_string(), inline_markdown),
+                    markdown(&exception.description),
+                ]
+            })
+            .collect::<Vec<_>>();
+        write_pipe_table(
+            out,
+            &["Vector", "Mnemonic", "Error Code", "Description"].map(String::from),
+            &rows,
+        );
+    }
+
+    fn write_list_of_tables_markdown(&self, out: &mut String) {
+        let rows = self
+            .registry
+            .tables
+            .iter()
+            .map(|table| {
+                let href = self.tag.chapter_link(&table.chapter, Some(&table.anchor));
+                vec![
+                    link(&format!("Table {}", table.number), &href),
+                    inline_markdown(&table.caption),
+                    link(
+                        &table.chapter_name,
+                        &self.tag.chapter_link(&table.chapter, None),
+                    ),
+                ]
+            })
+            .collect::<Vec<_>>();
+        write_pipe_table(
+            out,
+            &["Table", "Caption", "Chapter"].map(String::from),
+            &rows,
+        );
+    }
+
+    fn write_extension_table_markdown(&self, out: &mut String) {
+        let defs = &self.registry.extension_defs;
+        let links = |names: Vec<&str>| {
+            names
+                .iter()
+                .map(|name| self.extension_link(name))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        let rows = defs
+            .iter()
+            .map(|def| {
+                let version = def.version.as_ref().map_or_else(String::new, |version| {
+                    let href = self
+                        .tag
+                        .chapter_link(format!("versions/{version}.md").as_ref(), None);
+                    link(version, &href)
+                });
+                let required_by = defs
+                    .iter()
+                    .filter(|d| d.requires.contains(&def.name))
+                    .map(|d| &*d.name)
+                    .collect();
+                vec![
+                    self.extension_link(&def.name),
+                    version,
+                    links(def.requires.iter().map(|r| &**r).collect()),
+                    links(required_by),
+                ]
+            })
+            .collect::<Vec<_>>();
+        write_pipe_table(
+            out,
+            &["Extension", "Version", "Requires", "Required By"].map(String::from),
+            &rows,
+        );
+    }
+
+    fn extension_colour(&self, ext: &str) -> &'static str {
+        let idx = self
+            .registry
+            .extensions()
+            .iter()
+            .position(|&e| e == ext)
+            .unwrap_or(0);
+        PALETTE[idx % PALETTE.len()]
+    }
+
+    fn write_opcode_map<W: io::Write>(&self, w: &mut EventWriter<W>) -> xml::writer::Result<()> {
+        w.write(XmlEvent::start_element("div").attr("style", "overflow-x: auto"))?;
+        w.write(
+            XmlEvent::start_element("table")
+                .attr("class", "clever-opcode-map")
+                .attr("style", "font-size: 0.6em; border-collapse: collapse"),
+        )?;
+
+        w.write(XmlEvent::start_element("thead"))?;
+        w.write(XmlEvent::start_element("tr"))?;
+        w.write(XmlEvent::start_element("th"))?;
+        w.write(XmlEvent::end_element())?;
+        for col in 0..0o100 {
+            w.write(XmlEvent::start_element("th"))?;
+            w.write(XmlEvent::characters(&format!("{col:02o}")))?;
+            w.write(XmlEvent::end_element())?;
+        }
+        w.write(XmlEvent::end_element())?;
+        w.write(XmlEvent::end_element())?;
+
+        w.write(XmlEvent::start_element("tbody"))?;
+        for row in 0..0o100u16 {
+            w.write(XmlEvent::start_element("tr"))?;
+            w.write(XmlEvent::start_element("th"))?;
+            w.write(XmlEvent::characters(&format!("0o{row:02o}xx")))?;
+            w.write(XmlEvent::end_element())?;
+
+            for col in 0..0o100u16 {
+                let opcode = (row << 6) | col;
+                match self.registry.instruction(opcode) {
+                    Some(insn) => {
+                        let ext = insn.extension_name();
+                        let style = format!("background-color: {}", self.extension_colour(ext));
+                        let title = format!("{opcode:#06o}: {} (X-{ext})", insn.mnemonic);
+                        let href = self.tag.chapter_link(&insn.chapter, insn.anchor.as_deref());
+                        w.write(XmlEvent::start_element("td").attr("style", &style))?;
+                        w.write(
+                            XmlEvent::start_element("a")
+                                .attr("href", &href)
+                                .attr("title", &title),
+                        )?;
+                        w.write(XmlEvent::characters(&insn.mnemonic))?;
+                        w.write(XmlEvent::end_element())?;
+                        w.write(XmlEvent::end_element())?;
+                    }
+                    None => {
+                        w.write(XmlEvent::start_element("td"))?;
+                        w.write(XmlEvent::end_element())?;
+                    }
+                }
+            }
+            w.write(XmlEvent::end_element())?;
+        }
+        w.write(XmlEvent::end_element())?;
+        w.write(XmlEvent::end_element())?;
+        w.write(XmlEvent::end_element())?;
+
+        self.write_extension_legend(w)?;
+        self.write_free_ranges(w)
+    }
+
+    fn write_extension_legend<W: io::Write>(
+        &self,
+        w: &mut EventWriter<W>,
+    ) -> xml::writer::Result<()> {
+        w.write(XmlEvent::start_element("table").attr("class", "clever-opcode-legend"))?;
+        write_head(w, &["Extension", "Opcodes"])?;
+        w.write(XmlEvent::start_element("tbody"))?;
+        for ext in self.registry.extensions() {
+            let count = self
+                .registry
+                .instructions
+                .iter()
+                .filter(|i| i.extension_name() == ext)
+                .count();
+            let style = format!("background-color: {}", self.extension_colour(ext));
+            let href = self
+                .tag
+                .chapter_link(format!("extensions/{ext}.md").as_ref(), None);
+
+            w.write(XmlEvent::start_element("tr"))?;
+            w.write(XmlEvent::start_element("td").attr("style", &style))?;
+            w.write(XmlEvent::start_element("a").attr("href", &href))?;
+            w.write(XmlEvent::characters(&format!("X-{ext}")))?;
+            w.write(XmlEvent::end_element())?;
+            w.write(XmlEvent::end_element())?;
+            w.write(XmlEvent::start_element("td"))?;
+            w.write(XmlEvent::characters(&count.to_string()))?;
+            w.write(XmlEvent::end_element())?;
+            w.write(XmlEvent::end_element())?;
+        }
+        w.write(XmlEvent::end_element())?;
+        w.write(XmlEvent::end_element())
+    }
+
+    fn write_free_ranges<W: io::Write>(&self, w: &mut EventWriter<W>) -> xml::writer::Result<()> {
+        w.write(XmlEvent::start_element("table").attr("class", "clever-opcode-free"))?;
+        write_head(w, &["Free Opcodes", "Count"])?;
+        w.write(XmlEvent::start_element("tbody"))?;
+        for (start, end) in self.registry.free_ranges() {
+            let range = if start == end {
+                format!("{start:#06o}")
+            } else {
+                format!("{start:#06o}\u{2013}{end:#06o}")
+            };
+            w.write(XmlEvent::start_element("tr"))?;
+            w.write(XmlEvent::start_element("td"))?;
+            w.write(XmlEvent::start_element("code"))?;
+            w.write(XmlEvent::characters(&range))?;
+            w.write(XmlEvent::end_element())?;
+            w.write(XmlEvent::end_element())?;
+            w.write(XmlEvent::start_element("td"))?;
+            w.write(XmlEvent::characters(&(end - start + 1).to_string()))?;
+            w.write(XmlEvent::end_element())?;
+            w.write(XmlEvent::end_element())?;
+        }
+        w.write(XmlEvent::end_element())?;
+        w.write(XmlEvent::end_element())
+    }
+
+    fn write_exception_summary<W: io::Write>(
+        &self,
+        w: &mut EventWriter<W>,
+    ) -> xml::writer::Result<()> {
+        w.write(XmlEvent::start_element("table").attr("class", "clever-exception-summary"))?;
+        write_head(w, &["Vector", "Mnemonic", "Error Code", "Description"])?;
+        w.write(XmlEvent::start_element("tbody"))?;
+        for exception in &self.registry.exceptions {
+            let href = self
+                .tag
+                .chapter_link(&exception.chapter, Some(&exception.anchor()));
+
+            w.write(XmlEvent::start_element("tr"))?;
+            w.write(XmlEvent::start_element("td"))?;
+            Exception::write_vector_xhtml(exception.vector, w)?;
+            w.write(XmlEvent::end_element())?;
+            w.write(XmlEvent::start_element("td"))?;
+            w.write(XmlEvent::start_element("a").attr("href", &href))?;
+            w.write(XmlEvent::characters(&exception.name))?;
+            w.write(XmlEvent::end_element())?;
+            w.write(XmlEvent::end_element())?;
+            let [code, description] = [&exception.code, &exception.description].map(|markdown| {
+                markdown
+                    .as_deref()
+                    .map(|markdown| Elem::MarkdownLiteral(Cow::Borrowed(markdown)))
+            });
+            w.write(XmlEvent::start_element("td"))?;
+            let code = code.as_ref().map(Elem::to_rich_text);
+            Exception::write_code_xhtml(code.as_ref(), w)?;
+            w.write(XmlEvent::end_element())?;
+            w.write(XmlEvent::start_element("td"))?;
+            if let Some(description) = &description {
+                write_rich_node(&description.to_rich_text(), w)?;
+            }
+            w.write(XmlEvent::end_element())?;
+            w.write(XmlEvent::end_element())?;
+        }
+        w.write(XmlEvent::end_element())?;
+        w.write(XmlEvent::end_element())
+    }
+
+    /// Writes a table of every captioned table in the book, in the order they appear
+    fn write_list_of_tables<W: io::Write>(
+        &self,
+        w: &mut EventWriter<W>,
+    ) -> xml::writer::Result<()> {
+        w.write(XmlEvent::start_element("table").attr("class", "clever-list-of-tables"))?;
+        write_head(w, &["Table", "Caption", "Chapter"])?;
+        w.write(XmlEvent::start_element("tbody"))?;
+        for table in &self.registry.tables {
+            let href = self.tag.chapter_link(&table.chapter, Some(&table.anchor));
+            let elem = Elem::MarkdownLiteral(Cow::Borrowed(&table.caption));
+
+            w.write(XmlEvent::start_element("tr"))?;
+            w.write(XmlEvent::start_element("td"))?;
+            w.write(XmlEvent::start_element("a").attr("href", &href))?;
+            w.write(XmlEvent::characters(&format!("Table {}", table.number)))?;
+            w.write(XmlEvent::end_element())?;
+            w.write(XmlEvent::end_element())?;
+            w.write(XmlEvent::start_element("td"))?;
+            write_rich_node(&elem.to_rich_text(), w)?;
+            w.write(XmlEvent::end_element())?;
+            w.write(XmlEvent::start_element("td"))?;
+            w.write(
+                XmlEvent::start_element("a")
+                    .attr("href", &self.tag.chapter_link(&table.chapter, None)),
+            )?;
+            w.write(XmlEvent::characters(&table.chapter_name))?;
+            w.write(XmlEvent::end_element())?;
+            w.write(XmlEvent::end_element())?;
+            w.write(XmlEvent::end_element())?;
+        }
+        w.write(XmlEvent::end_element())?;
+        w.write(XmlEvent::end_element())
+    }
+
+    fn version_colour(&self, version: Option<&str>) -> &'static str {
+        let mut versions = Vec::new();
+        for def in &self.registry.extension_defs {
+            if !versions.contains(&def.version.as_deref()) {
+                versions.push(def.version.as_deref());
+            }
+        }
+        let idx = versions.iter().position(|&v| v == version).unwrap_or(0);
+        PALETTE[idx % PALETTE.len()]
+    }
+
+    /// Writes an SVG diagram of the requirements between extensions, with each extension placed in a column after
+    /// all of its prerequisites, followed by a table of the same information
+    fn write_extension_graph<W: io::Write>(
+        &self,
+        w: &mut EventWriter<W>,
+    ) -> xml::writer::Result<()> {
+        const NODE_WIDTH: usize = 140;
+        const NODE_HEIGHT: usize = 30;
+        const COLUMN_GAP: usize = 60;
+        const ROW_GAP: usize = 16;
+
+        let defs = &self.registry.extension_defs;
+
+        let mut columns: Vec<Vec<usize>> = Vec::new();
+        let mut position = vec![(0, 0); defs.len()];
+        for (idx, def) in defs.iter().enumerate() {
+            let depth = self.registry.extension_depth(def);
+            if columns.len() <= depth {
+                columns.resize_with(depth + 1, Vec::new);
+            }
+            position[idx] = (depth, columns[depth].len());
+            columns[depth].push(idx);
+        }
+
+        let node_x = |col: usize| col * (NODE_WIDTH + COLUMN_GAP);
+        let node_y = |row: usize| row * (NODE_HEIGHT + ROW_GAP);
+        let width = node_x(columns.len()).saturating_sub(COLUMN_GAP);
+        let height =
+            node_y(columns.iter().map(Vec::len).max().unwrap_or(0)).saturating_sub(ROW_GAP);
+
+        w.write(XmlEvent::start_element("div").attr("style", "overflow-x: auto"))?;
+        w.write(
+            XmlEvent::start_element("svg")
+                .default_ns("http://www.w3.org/2000/svg")
+                .attr("class", "clever-extension-graph")
+                .attr("width", &width.to_string())
+                .attr("height", &height.to_string())
+                .attr("viewBox", &format!("0 0 {width} {height}")),
+        )?;
+
+        w.write(XmlEvent::start_element("defs"))?;
+        w.write(
+            XmlEvent::start_element("marker")
+                .attr("id", "clever-extension-arrow")
+                .attr("markerWidth", "8")
+                .attr("markerHeight", "8")
+                .attr("refX", "8")
+                .attr("refY", "4")
+                .attr("orient", "auto"),
+        )?;
+        w.write(XmlEvent::start_element("path").attr("d", "M0,0 L8,4 L0,8 z"))?;
+        w.write(XmlEvent::end_element())?;
+        w.write(XmlEvent::end_element())?;
+        w.write(XmlEvent::end_element())?;
+
+        for (idx, def) in defs.iter().enumerate() {
+            let (col, row) = position[idx];
+            for req in &def.requires {
+                let Some(prereq) = defs.iter().position(|d| d.name == *req) else {
+                    continue;
+                };
+                let (req_col, req_row) = position[prereq];
+                w.write(
+                    XmlEvent::start_element("line")
+                        .attr("x1", &(node_x(req_col) + NODE_WIDTH).to_string())
+                        .attr("y1", &(node_y(req_row) + NODE_HEIGHT / 2).to_string())
+                        .attr("x2", &node_x(col).to_string())
+                        .attr("y2", &(node_y(row) + NODE_HEIGHT / 2).to_string())
+                        .attr("stroke", "currentColor")
+                        .attr("marker-end", "url(#clever-extension-arrow)"),
+                )?;
+                w.write(XmlEvent::end_element())?;
+            }
+        }
+
+        for (idx, def) in defs.iter().enumerate() {
+            let (col, row) = position[idx];
+            let (x, y) = (node_x(col), node_y(row));
+            let href = self.tag.chapter_link(&def.chapter, None);
+            let title = match &def.version {
+                Some(version) => format!("X-{} (version {version})", def.name),
+                None => format!("X-{}", def.name),
+            };
+
+            w.write(XmlEvent::start_element("a").attr("href", &href))?;
+            w.write(XmlEvent::start_element("title"))?;
+            w.write(XmlEvent::characters(&title))?;
+            w.write(XmlEvent::end_element())?;
+            w.write(
+                XmlEvent::start_element("rect")
+                    .attr("x", &x.to_string())
+                    .attr("y", &y.to_string())
+                    .attr("width", &NODE_WIDTH.to_string())
+                    .attr("height", &NODE_HEIGHT.to_string())
+                    .attr("rx", "4")
+                    .attr("fill", self.version_colour(def.version.as_deref()))
+                    .attr("stroke", "black"),
+            )?;
+            w.write(XmlEvent::end_element())?;
+            w.write(
+                XmlEvent::start_element("text")
+                    .attr("x", &(x + NODE_WIDTH / 2).to_string())
+                    .attr("y", &(y + NODE_HEIGHT / 2).to_string())
+                    .attr("text-anchor", "middle")
+                    .attr("dominant-baseline", "central")
+                    .attr("fill", "black"),
+            )?;
+            w.write(XmlEvent::characters(&format!("X-{}", def.name)))?;
+            w.write(XmlEvent::end_element())?;
+            w.write(XmlEvent::end_element())?;
+        }
+
+        w.write(XmlEvent::end_element())?;
+        w.write(XmlEvent::end_element())?;
+
+        self.write_extension_table(w)
+    }
+
+    fn write_extension_table<W: io::Write>(
+        &self,
+        w: &mut EventWriter<W>,
+    ) -> xml::writer::Result<()> {
+        let defs = &self.registry.extension_defs;
+
+        let write_links = |w: &mut EventWriter<W>, names: &[&str]| -> xml::writer::Result<()> {
+            for (idx, name) in names.iter().enumerate() {
+                if idx != 0 {
+                    w.write(XmlEvent::characters(", "))?;
+                }
+                let href = self
+                    .tag
+                    .chapter_link(format!("extensions/{name}.md").as_ref(), None);
+                w.write(XmlEvent::start_element("a").attr("href", &href))?;
+                w.write(XmlEvent::characters(&format!("X-{name}")))?;
+                w.write(XmlEvent::end_element())?;
+            }
+            Ok(())
+        };
+
+        w.write(XmlEvent::start_element("table").attr("class", "clever-extension-table"))?;
+        write_head(w, &["Extension", "Version", "Requires", "Required By"])?;
+        w.write(XmlEvent::start_element("tbody"))?;
+        for def in defs {
+            let requires = def.requires.iter().map(|r| &**r).collect::<Vec<_>>();
+            let required_by = defs
+                .iter()
+                .filter(|d| d.requires.contains(&def.name))
+                .map(|d| &*d.name)
+                .collect::<Vec<_>>();
+
+            w.write(XmlEvent::start_element("tr"))?;
+            w.write(XmlEvent::start_element("td"))?;
+            write_links(w, &[&def.name])?;
+            w.write(XmlEvent::end_element())?;
+            w.write(XmlEvent::start_element("td"))?;
+            if let Some(version) = &def.version {
+                let href = self
+                    .tag
+                    .chapter_link(format!("versions/{version}.md").as_ref(), None);
+                w.write(XmlEvent::start_element("a").attr("href", &href))?;
+                w.write(XmlEvent::characters(version))?;
+                w.write(XmlEvent::end_element())?;
+            }
+            w.write(XmlEvent::end_element())?;
+            w.write(XmlEvent::start_element("td"))?;
+            write_links(w, &requires)?;
+            w.write(XmlEvent::end_element())?;
+            w.write(XmlEvent::start_element("td"))?;
+            write_links(w, &required_by)?;
+            w.write(XmlEvent::end_element())?;
+            w.write(XmlEvent::end_element())?;
+        }
+        w.write(XmlEvent::end_element())?;
+        w.write(XmlEvent::end_element())
+    }
+}
+
+fn write_head<W: io::Write>(w: &mut EventWriter<W>, heading: &[&str]) -> xml::writer::Result<()> {
+    w.write(XmlEvent::start_element("thead"))?;
+    w.write(XmlEvent::start_element("tr"))?;
+    for h in heading {
+        w.write(XmlEvent::start_element("th"))?;
+        w.write(XmlEvent::characters(h))?;
+        w.write(XmlEvent::end_element())?;
+    }
+    w.write(XmlEvent::end_element())?;
+    w.write(XmlEvent::end_element())
+}
@@ -1,6 +1,15 @@
-use std::{fmt::Write, io};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write,
+    io,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use mdbook::book::Chapter;
 
 use pulldown_cmark::{BrokenLinkCallback, CowStr};
+use xml::{EmitterConfig, EventWriter};
 
 pub struct StringAppender<'a>(pub &'a mut String);
 
@@ -27,20 +36,184 @@ impl<'a> io::Write for StringAppender<'a> {
     }
 
     fn write_fmt(&mut self, fmt: std::fmt::Arguments<'_>) -> io::Result<()> {
-        self.0
-            .write_fmt(fmt)
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+        self.0.write_fmt(fmt).map_err(io::Error::other)
+    }
+}
+
+/// The directory containing the source file of `c`, which paths in the chapter are relative to
+pub fn chapter_dir(src_dir: &Path, c: &Chapter) -> PathBuf {
+    let path = c.source_path.as_deref().or(c.path.as_deref());
+    match path.and_then(Path::parent) {
+        Some(parent) => src_dir.join(parent),
+        None => src_dir.to_path_buf(),
+    }
+}
+
+/// Replaces each byte range of `content` with its text, keeping the rest of `content` as it is.
+///
+/// Edits are applied in order of their start, then of their end, then of their position in `edits`, so an empty range
+/// inserts its text before any replacement starting at the same offset. Gives the range of the first edit that
+/// overlaps an earlier one as the error, since only one of them could be applied.
+pub fn splice(content: &str, edits: Vec<(Range<usize>, String)>) -> Result<String, Range<usize>> {
+    let mut edits = edits.into_iter().enumerate().collect::<Vec<_>>();
+    edits.sort_by_key(|(idx, (range, _))| (range.start, range.end, *idx));
+
+    let mut out = String::with_capacity(content.len());
+    let mut pos = 0;
+    for (_, (range, text)) in edits {
+        if range.start < pos {
+            return Err(range);
+        }
+        out.push_str(&content[pos..range.start]);
+        out.push_str(&text);
+        pos = range.end;
+    }
+    out.push_str(&content[pos..]);
+    Ok(out)
+}
+
+/// The prefix that continues the list items and block quotes of the line containing `offset` onto following lines,
+/// such as `   ` for a block inside `1. ` or `> ` inside a block quote
+pub fn continuation_prefix(content: &str, offset: usize) -> String {
+    let line_start = content[..offset].rfind('\n').map_or(0, |idx| idx + 1);
+    content[line_start..offset]
+        .chars()
+        .map(|c| {
+            if c == '>' || c.is_whitespace() {
+                c
+            } else {
+                ' '
+            }
+        })
+        .collect()
+}
+
+/// Starts every line of `text` after the first with `prefix`, so that text replacing a block stays inside the list
+/// items and block quotes that contained the block. Blank lines only get the block quote markers.
+pub fn indent_lines(text: &str, prefix: &str) -> String {
+    if prefix.is_empty() {
+        return text.to_string();
     }
+
+    let mut lines = text.split('\n');
+    let mut out = lines.next().unwrap_or_default().to_string();
+    for line in lines {
+        out.push('\n');
+        if line.is_empty() {
+            out.push_str(prefix.trim_end());
+        } else {
+            out.push_str(prefix);
+            out.push_str(line);
+        }
+    }
+    out
+}
+
+pub fn xml_writer<W: io::Write>(sink: W) -> EventWriter<W> {
+    EventWriter::new_with_config(
+        sink,
+        EmitterConfig::new()
+            .write_document_declaration(false)
+            .normalize_empty_elements(false)
+            .cdata_to_characters(true),
+    )
+}
+
+/// How links between chapters are written for the renderer the book is being built for
+#[derive(Copy, Clone, Debug)]
+pub enum LinkStyle<'a> {
+    /// Links to the `.md` sources, relative to the chapter, for the `markdown` renderer and any others
+    Source,
+    /// Links to the `.html` pages of the `html` renderer, relative to the chapter unless `site-url` is configured
+    Html { site_url: Option<&'a str> },
+    /// Links to the XHTML files of the `epub-fancy` renderer, which are named by the file id of each chapter
+    Epub {
+        /// The `[output.epub-fancy.file-ids]` table, from chapter path to file id
+        file_ids: &'a HashMap<String, String>,
+    },
+}
+
+/// The file id of a chapter in an EPUB when none is configured, such as `extensions-main` for `extensions/main.md`
+pub fn default_file_id(path: &Path) -> String {
+    path.with_extension("")
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// The path of `target` relative to the directory `from`, with both relative to the book's `src` directory
+fn relative_path(from: &Path, target: &Path) -> PathBuf {
+    let from = from.components().collect::<Vec<_>>();
+    let target = target.components().collect::<Vec<_>>();
+    let common = from.iter().zip(&target).take_while(|(a, b)| a == b).count();
+
+    let mut path = PathBuf::new();
+    for _ in common..from.len() {
+        path.push("..");
+    }
+    path.extend(&target[common..]);
+    path
 }
 
 #[derive(Copy, Clone)]
 pub struct TagExpander<'a> {
-    base: &'a str,
+    style: LinkStyle<'a>,
+    /// The directory of the chapters named by each tag prefix, from the `tag-groups` configuration
+    tag_groups: &'a BTreeMap<String, PathBuf>,
+    /// The chapter that links are written in, which they are relative to unless `site-url` is configured
+    chapter: Option<&'a Path>,
 }
 
 impl<'a> TagExpander<'a> {
-    pub const fn new(base_url: &'a str) -> Self {
-        Self { base: base_url }
+    pub const fn new(
+        style: LinkStyle<'a>,
+        tag_groups: &'a BTreeMap<String, PathBuf>,
+        chapter: Option<&'a Path>,
+    ) -> Self {
+        Self {
+            style,
+            tag_groups,
+            chapter,
+        }
+    }
+
+    /// The chapter that links are written in
+    pub const fn chapter(&self) -> Option<&'a Path> {
+        self.chapter
+    }
+
+    /// The link to the chapter with source `path`, relative to the book's `src` directory
+    fn link_to(&self, path: &Path) -> String {
+        let path = match self.style {
+            LinkStyle::Source => path.to_path_buf(),
+            LinkStyle::Html { site_url } => {
+                let page = path.with_extension("html");
+                if let Some(site_url) = site_url {
+                    return format!("{site_url}/{}", page.display());
+                }
+                page
+            }
+            // Every file of an EPUB is in the same directory, so links are never relative to the chapter
+            LinkStyle::Epub { file_ids } => {
+                let id = file_ids
+                    .get(&*path.to_string_lossy())
+                    .cloned()
+                    .unwrap_or_else(|| default_file_id(path));
+                return format!("{id}.xhtml");
+            }
+        };
+
+        let link = match self.chapter {
+            Some(chapter) => relative_path(chapter.parent().unwrap_or(Path::new("")), &path),
+            None => path,
+        };
+
+        // Links use `/` as the separator on every platform
+        link.components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/")
     }
 
     pub fn resolve_link<'b>(&self, link: CowStr<'b>) -> Option<(CowStr<'b>, CowStr<'b>)> {
@@ -51,22 +224,20 @@ impl<'a> TagExpander<'a> {
 
         let (group, tail) = tag.split_once("-")?;
 
-        let intermediate = match group {
-            "D" => "documents",
-            "X" => "extensions",
-            "R" => "reports",
-            "V" => {
-                // TODO;
-                return None;
-            }
-            _ => return None,
-        };
-        let base = self.base;
-
-        let st = format!("{base}/{intermediate}/{tail}.md");
+        let dir = self.tag_groups.get(group)?;
+        let st = self.link_to(&dir.join(format!("{tail}.md")));
 
         Some((CowStr::from(st), CowStr::from(tag).into_static()))
     }
+
+    pub fn chapter_link(&self, path: &Path, anchor: Option<&str>) -> String {
+        match anchor {
+            // Links within the same chapter only need the anchor
+            Some(anchor) if self.chapter == Some(path) => format!("#{anchor}"),
+            Some(anchor) => format!("{}#{anchor}", self.link_to(path)),
+            None => self.link_to(path),
+        }
+    }
 }
 
 impl<'input, 'a> BrokenLinkCallback<'input> for TagExpander<'a> {
@@ -81,3 +252,80 @@ impl<'input, 'a> BrokenLinkCallback<'input> for TagExpander<'a> {
         self.resolve_link(link.reference)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splice_replaces_and_inserts() {
+        let edits = vec![
+            (6..11, "there".to_string()),
+            (0..0, "> ".to_string()),
+            (11..11, "!".to_string()),
+        ];
+        assert_eq!(splice("hello world", edits).unwrap(), "> hello there!");
+    }
+
+    #[test]
+    fn splice_inserts_before_a_replacement_at_the_same_offset() {
+        let edits = vec![
+            (0..5, "bye".to_string()),
+            (0..0, "<a>".to_string()),
+            (0..0, "<b>".to_string()),
+        ];
+        assert_eq!(splice("hello", edits).unwrap(), "<a><b>bye");
+    }
+
+    #[test]
+    fn splice_rejects_overlapping_edits() {
+        let edits = vec![(2..20, "[link]".to_string()), (10..15, "table".to_string())];
+        assert_eq!(splice("See [the map here][X-main]", edits), Err(10..15));
+
+        let edits = vec![(0..5, "a".to_string()), (3..3, "b".to_string())];
+        assert_eq!(splice("hello", edits), Err(3..3));
+    }
+
+    #[test]
+    fn continuation_prefix_keeps_containers() {
+        let content = "1. Item\n\n   ```x\n> ```y\n1. ```z\n";
+        assert_eq!(
+            continuation_prefix(content, content.find("```x").unwrap()),
+            "   "
+        );
+        assert_eq!(
+            continuation_prefix(content, content.find("```y").unwrap()),
+            "> "
+        );
+        assert_eq!(
+            continuation_prefix(content, content.find("```z").unwrap()),
+            "   "
+        );
+        assert_eq!(continuation_prefix(content, 0), "");
+    }
+
+    #[test]
+    fn indent_lines_skips_the_first_line() {
+        assert_eq!(indent_lines("\n<table>\n\n", "   "), "\n   <table>\n\n");
+        assert_eq!(indent_lines("a\n\nb", "> "), "a\n>\n> b");
+        assert_eq!(indent_lines("a\nb", ""), "a\nb");
+    }
+
+    #[test]
+    fn relative_path_between_chapters() {
+        let path = |from: &str, target: &str| relative_path(Path::new(from), Path::new(target));
+        assert_eq!(
+            path("", "extensions/main.md"),
+            Path::new("extensions/main.md")
+        );
+        assert_eq!(
+            path("extensions", "extensions/main.md"),
+            Path::new("main.md")
+        );
+        assert_eq!(
+            path("versions", "extensions/main.md"),
+            Path::new("../extensions/main.md")
+        );
+        assert_eq!(path("a/b", "c.md"), Path::new("../../c.md"));
+    }
+}
@@ -1,25 +1,151 @@
-use std::io;
+use std::{collections::HashMap, io, ops::Range, path::Path};
 
-use helpers::StringAppender;
-use logos::Logos;
 use mdbook::{BookItem, book::Chapter, preprocess::CmdPreprocessor};
-use mdbook_fiction_tools::xhtml::{write_rich_node, xml_to_io_error};
-use nom::{Finish, combinator};
-use pulldown_cmark::{BrokenLinkCallback, CodeBlockKind, Event, Options, Parser, Tag, TagEnd};
-use pulldown_cmark_to_cmark::cmark_resume;
-use spec_lang::{Error, Spanned, Token, parse::parse_elem};
-use xml::{EmitterConfig, EventWriter};
+use pulldown_cmark::{
+    BrokenLinkCallback, CodeBlockKind, CowStr, Event, Options, Parser, Tag, TagEnd,
+};
 
+mod asm;
+mod codegen;
+mod config;
+mod data;
+mod generate;
 mod helpers;
+mod registry;
+mod render;
 mod spec_lang;
 
-fn handle_chapter(c: &mut Chapter, base_url: &str) -> io::Result<()> {
+use spec_lang::BlockMode;
+
+/// The renderers that the output of the preprocessor is written for
+const SUPPORTED_RENDERERS: [&str; 3] = ["html", "markdown", "epub-fancy"];
+
+/// An error at the byte `offset` of a chapter's `content`, prefixed with the chapter and line
+fn located_error(
+    content: &str,
+    offset: usize,
+    chapter: Option<&Path>,
+    msg: impl core::fmt::Display,
+) -> io::Error {
+    let line = content[..offset].matches('\n').count() + 1;
+    let chapter = chapter.map_or_else(String::new, |path| format!("{}:", path.display()));
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{chapter}{line}: {msg}"),
+    )
+}
+
+/// Checks a code block written in the specification language, and renders it unless its mode keeps the source
+fn spec_block(
+    mode: BlockMode,
+    source: &spec_lang::Source,
+    config: &config::Config,
+    registry: &registry::Registry,
+    tag: &helpers::TagExpander,
+    backend: &dyn render::Backend,
+) -> io::Result<Option<String>> {
+    if mode == BlockMode::Ignore {
+        return Ok(None);
+    }
+
+    let mut item = source.parse()?;
+    data::load_item(&mut item.body, &source.dir)?;
+    registry.link_exception_cells(&mut item.body, tag);
+    registry.number_table(&mut item.body, tag);
+
+    // A block that fails its checks is left as source, as its rendering would be meaningless
+    let checked = source.check(&item.body);
+    let failed = checked.is_err();
+    config.checks.spec_blocks.report(checked)?;
+
+    if failed || !mode.is_rendered() {
+        return Ok(None);
+    }
+
+    let mut out = String::from("\n");
+    backend.write_item(&item.body, &mut out)?;
+    out.push_str("\n\n");
+
+    Ok(Some(out))
+}
+
+/// A fenced code block with the language `lang` holding `body`
+fn fenced_block(lang: &str, body: &str) -> String {
+    // The fence is longer than any run of backticks in the source, so that the source cannot close it
+    let longest = body.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest.max(2) + 1);
+    let newline = if body.ends_with('\n') { "" } else { "\n" };
+
+    format!("{fence}{lang}\n{body}{newline}{fence}\n")
+}
+
+/// The source of a code block with the language `lang`, as a fenced code block within a collapsible section
+fn source_details(lang: &str, body: &str) -> String {
+    format!(
+        "<details class=\"clever-spec-source\">\n<summary>Source</summary>\n\n{}\n</details>\n\n",
+        fenced_block(lang, body)
+    )
+}
+
+/// Collects the contents of a fenced code block whose start has just been read from `parser`.
+///
+/// Code blocks only hold text, but any other event is reported as an error with the line of `content` it is on,
+/// rather than being dropped from the block.
+fn code_block_body<'a>(
+    parser: &mut impl Iterator<Item = (Event<'a>, Range<usize>)>,
+    content: &str,
+    chapter: Option<&Path>,
+    lang: &str,
+) -> io::Result<String> {
+    let mut body = String::new();
+
+    for (event, range) in parser {
+        match event {
+            Event::Text(text) | Event::Html(text) | Event::InlineHtml(text) => body.push_str(&text),
+            Event::SoftBreak | Event::HardBreak => body.push('\n'),
+            Event::End(TagEnd::CodeBlock) => return Ok(body),
+            event => {
+                return Err(located_error(
+                    content,
+                    range.start,
+                    chapter,
+                    format!("Unexpected {event:?} in a `{}` code block", lang.trim()),
+                ));
+            }
+        }
+    }
+
+    // The parser closes every block at the end of the chapter, so this is only reached if it is given a partial chapter
+    Ok(body)
+}
+
+/// A link to a tag, such as `[X-float]`, which is rewritten to link to the tag's chapter
+struct TagLink<'a> {
+    /// The source of the whole link
+    range: Range<usize>,
+    dest: CowStr<'a>,
+    title: CowStr<'a>,
+    /// The source of the link's text, so far as it has been read
+    text: Option<Range<usize>>,
+}
+
+fn handle_chapter(
+    c: &mut Chapter,
+    links: helpers::LinkStyle,
+    src_dir: &Path,
+    config: &config::Config,
+    registry: &registry::Registry,
+    backend: &dyn render::Backend,
+) -> io::Result<()> {
     if let Some(path) = c.path.as_deref() {
         eprintln!("Visiting Chapter: {}", path.display());
     }
+    let dir = helpers::chapter_dir(src_dir, c);
     let content = core::mem::take(&mut c.content);
 
-    let tag = helpers::TagExpander::new(base_url);
+    let tag = helpers::TagExpander::new(links, &config.tag_groups, c.path.as_deref());
+
+    let generator = generate::Generator::new(registry, tag, backend);
 
     let mut parser = Parser::new_with_broken_link_callback(
         &content,
@@ -28,95 +154,175 @@ fn handle_chapter(c: &mut Chapter, base_url: &str) -> io::Result<()> {
             | Options::ENABLE_STRIKETHROUGH
             | Options::ENABLE_HEADING_ATTRIBUTES,
         Some(tag),
-    );
+    )
+    .into_offset_iter();
+
+    // Only the code blocks and links that are rewritten are replaced, so every other byte of the chapter is kept
+    let mut edits: Vec<(Range<usize>, String)> = Vec::new();
 
-    let mut state = None;
+    // Inline code is only auto-linked outside of links and headings, which are already links in the html output
+    let mut link_depth = 0usize;
+    let mut in_heading = false;
 
-    let mut events = Vec::new();
+    let mut tag_link: Option<TagLink> = None;
+
+    // Property definitions in markdown tables get an anchor at the start of their row, so that uses can link to them
+    let mut row_properties = match c.path.as_deref() {
+        Some(path) => registry.row_properties(path).collect::<Vec<_>>(),
+        None => Vec::new(),
+    };
+    let mut row_start = false;
+    let mut first_cell = None;
+
+    // Directives are only expanded in text, so that code blocks can show them
+    let mut in_code_block = false;
+
+    while let Some((event, range)) = parser.next() {
+        if let Some(link) = &mut tag_link
+            && !matches!(event, Event::End(TagEnd::Link))
+        {
+            let start = link.text.as_ref().map_or(range.start, |text| text.start);
+            link.text = Some(start..range.end);
+        }
+
+        // The anchor goes at the start of the cell, in case its text is inside a link that is rewritten. Each
+        // property is looked up by name, so that a row that is not a definition does not affect the rows after it.
+        if let Event::Text(text) | Event::Code(text) = &event
+            && let Some(start) = first_cell.take()
+            && let Some(idx) = row_properties.iter().position(|p| p.name == text.trim())
+        {
+            let prop = row_properties.remove(idx);
+            edits.push((start..start, format!("<a id=\"{}\"></a>", prop.anchor)));
+        }
 
-    while let Some(event) = parser.next() {
         match event {
-            Event::Start(Tag::CodeBlock(cb)) => match cb {
-                CodeBlockKind::Fenced(lang) if lang.trim() == "clever-spec,render" => {
-                    state = Some(
-                        cmark_resume(events.drain(..), &mut c.content, state.take())
-                            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
-                    );
-                    c.content.push_str("\n\n");
-                    let mut body = String::new();
-
-                    while let Some(e) = parser.next() {
-                        match e {
-                            Event::Text(text) => body.push_str(&text),
-                            Event::End(TagEnd::CodeBlock) => break,
-                            e => panic!("Got unexpected event {e:?}"),
+            Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(lang))) => {
+                let chapter = c.path.as_deref();
+                if let Some(info) = config.spec_block(&lang) {
+                    let located = |e| located_error(&content, range.start, chapter, e);
+                    let info = info.map_err(located)?;
+                    let body = code_block_body(&mut parser, &content, chapter, &lang)?;
+                    let spec_lang = lang.split(',').next().unwrap_or_default().trim();
+
+                    let source = spec_lang::Source::load(
+                        body,
+                        info.file,
+                        &dir,
+                        chapter.unwrap_or(Path::new("")),
+                    )
+                    .map_err(|e| located(e.to_string()))?;
+                    // Errors in an included file are already located in that file
+                    let out = spec_block(info.mode, &source, config, registry, &tag, backend)
+                        .map_err(|e| match source.file {
+                            Some(_) => e,
+                            None => located(e.to_string()),
+                        })?;
+
+                    // The replacement stays in any list item or block quote that the block is in
+                    let prefix = helpers::continuation_prefix(&content, range.start);
+                    match out {
+                        Some(mut out) => {
+                            if info.mode == BlockMode::RenderWithSource {
+                                out.push_str(&source_details(spec_lang, &source.text));
+                            }
+                            edits.push((range, helpers::indent_lines(&out, &prefix)));
                         }
+                        // The source of an included file is shown in place of the empty block
+                        None if source.file.is_some() => {
+                            let block = fenced_block(spec_lang, &source.text);
+                            edits.push((range, helpers::indent_lines(&block, &prefix)));
+                        }
+                        None => {}
                     }
-
-                    let tokens = Token::lexer(&body)
-                        .spanned()
-                        .map(|(r, s)| r.map(move |t| Spanned { body: t, span: s }))
-                        .collect::<Result<Vec<_>, Error>>()
-                        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
-
-                    let elem =
-                        combinator::all_consuming(combinator::complete(parse_elem))(&*tokens)
-                            .finish()
-                            .map(|(_, elem)| elem)
-                            .map_err(|e| {
-                                io::Error::new(
-                                    io::ErrorKind::InvalidData,
-                                    format!("Parse Error: {e:?}"),
-                                )
-                            })?;
-
-                    let rt = elem.body.to_rich_text();
-
-                    let mut writer = StringAppender(&mut c.content);
-                    let mut writer = EventWriter::new_with_config(
-                        &mut writer,
-                        EmitterConfig::new()
-                            .write_document_declaration(false)
-                            .cdata_to_characters(true),
-                    );
-                    write_rich_node(&rt, &mut writer).map_err(xml_to_io_error)?;
-
-                    c.content.push_str("\n\n");
+                } else {
+                    in_code_block = true;
                 }
-                _ => events.push(Event::Start(Tag::CodeBlock(cb))),
-            },
+            }
+            Event::Start(Tag::CodeBlock(CodeBlockKind::Indented)) => in_code_block = true,
+            Event::End(TagEnd::CodeBlock) => in_code_block = false,
             Event::Start(Tag::Link {
                 link_type,
                 dest_url,
                 title,
                 id,
             }) => {
+                link_depth += 1;
                 eprintln!(
                     "Link{{link_type: {link_type:?}, dest_url: {dest_url:?}, title: {title:?}, id:{id:?}}}"
                 );
-                if let Some((link, title)) = tag.resolve_link(id.clone()) {
-                    events.push(Event::Start(Tag::Link {
-                        link_type,
-                        dest_url: link,
+                if let Some((dest, title)) = tag.resolve_link(id) {
+                    tag_link = Some(TagLink {
+                        range,
+                        dest,
                         title,
-                        id,
-                    }))
-                } else {
-                    events.push(Event::Start(Tag::Link {
-                        link_type,
-                        dest_url,
-                        title,
-                        id,
-                    }))
+                        text: None,
+                    });
+                }
+            }
+            Event::End(TagEnd::Link) => {
+                link_depth -= 1;
+                if let Some(TagLink {
+                    range,
+                    dest,
+                    title,
+                    text,
+                }) = tag_link.take()
+                {
+                    let text = text.map_or("", |text| &content[text]);
+                    edits.push((range, format!("[{text}]({dest} \"{title}\")")));
                 }
             }
-            e => events.push(e),
+            Event::Start(Tag::Heading { .. }) | Event::End(TagEnd::Heading(_)) => {
+                in_heading = matches!(event, Event::Start(_));
+            }
+            Event::Start(Tag::TableRow) => row_start = true,
+            Event::Start(Tag::TableCell) => {
+                first_cell = core::mem::take(&mut row_start).then_some(range.start);
+            }
+            Event::End(TagEnd::TableCell) => first_cell = None,
+            Event::Text(_) => {
+                // The source of the text is searched, so that each directive is replaced where it was written
+                let mut from = range.start;
+                while !in_code_block
+                    && let Some(directive) = generate::find_directive(&content[from..range.end])
+                {
+                    let located = |offset, e| located_error(&content, offset, c.path.as_deref(), e);
+                    let (found, name) = directive.map_err(|e| located(from, e))?;
+                    let found = from + found.start..from + found.end;
+
+                    let mut out = String::from("\n\n");
+                    generator
+                        .expand_directive(name, &mut out)
+                        .map_err(|e| located(found.start, e))?;
+                    out.push_str("\n\n");
+
+                    from = found.end;
+                    edits.push((found, out));
+                }
+            }
+            Event::Code(code) if link_depth == 0 && !in_heading => {
+                let link = registry
+                    .register_field_link(&code, &tag)
+                    .or_else(|| registry.property_link(&code, &tag))
+                    .or_else(|| registry.exception_link(&code, &tag));
+                if let Some(link) = link {
+                    edits.push((range.clone(), format!("[{}]({link})", &content[range])));
+                }
+            }
+            _ => {}
         }
     }
 
-    cmark_resume(events.into_iter(), &mut c.content, state.take())
-        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
-        .map(|_| ())
+    c.content = helpers::splice(&content, edits).map_err(|range| {
+        located_error(
+            &content,
+            range.start,
+            c.path.as_deref(),
+            "This text cannot be rewritten, as it is inside other text that is rewritten, such as a link to a tag",
+        )
+    })?;
+
+    Ok(())
 }
 
 fn main() -> io::Result<()> {
@@ -124,7 +330,19 @@ fn main() -> io::Result<()> {
     args.next();
 
     match args.next().as_deref() {
-        Some("supports") => return Ok(()),
+        Some("supports") => {
+            let renderer = args.next().unwrap_or_default();
+            // mdbook runs the preprocessor from the book's root, but does not give it the configuration here
+            let config = mdbook::Config::from_disk("book.toml")
+                .ok()
+                .and_then(|config| config::Config::load(&config).ok())
+                .unwrap_or_default();
+            if !config.runs_for(&renderer, &SUPPORTED_RENDERERS) {
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+        Some("codegen") => return codegen::run(args),
         Some(s) => {
             return Err(io::Error::new(
                 io::ErrorKind::InvalidInput,
@@ -137,27 +355,66 @@ fn main() -> io::Result<()> {
     let (ctx, mut book) = CmdPreprocessor::parse_input(std::io::stdin())
         .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
 
-    let base = if ctx.renderer == "html" {
-        ctx.config
-            .get_renderer("html")
-            .and_then(|v| v.get("site-url"))
-            .and_then(|v| v.as_str())
-            .unwrap_or("/")
-            .trim_end_matches("/")
-    } else {
-        ""
+    let config = config::Config::load(&ctx.config)?;
+
+    if !config.runs_for(&ctx.renderer, &SUPPORTED_RENDERERS) {
+        eprintln!(
+            "Warning: The `{}` renderer is not supported, so the book is left unchanged",
+            ctx.renderer
+        );
+        return serde_json::to_writer(std::io::stdout(), &book).map_err(io::Error::other);
+    }
+
+    let file_ids = ctx
+        .config
+        .get("output.epub-fancy.file-ids")
+        .and_then(|v| v.as_table())
+        .into_iter()
+        .flatten()
+        .filter_map(|(path, id)| Some((path.clone(), id.as_str()?.to_string())))
+        .collect::<HashMap<_, _>>();
+
+    // Links are relative to the chapter they are in, unless the html renderer has a `site-url` to make them absolute to
+    let links = match &*ctx.renderer {
+        "html" => helpers::LinkStyle::Html {
+            site_url: ctx
+                .config
+                .get_renderer("html")
+                .and_then(|v| v.get("site-url"))
+                .and_then(|v| v.as_str())
+                .map(|url| url.trim_end_matches('/')),
+        },
+        "epub-fancy" => helpers::LinkStyle::Epub {
+            file_ids: &file_ids,
+        },
+        _ => helpers::LinkStyle::Source,
     };
 
+    let src_dir = ctx.root.join(&ctx.config.book.src);
+
+    let registry = registry::Registry::collect(&book, &src_dir, &config)?;
+    let backend = render::backend(&ctx.renderer);
+
+    if let Some(path) = &config.isa_description {
+        codegen::write_description(
+            &registry,
+            &ctx.root.join(&ctx.config.build.build_dir).join(path),
+        )?;
+    }
+
     let mut err = None;
 
     book.for_each_mut(|i| match i {
-        BookItem::Chapter(c) => err = handle_chapter(c, base).err().or(err.take()),
+        BookItem::Chapter(c) => {
+            err = handle_chapter(c, links, &src_dir, &config, &registry, &*backend)
+                .err()
+                .or(err.take())
+        }
         _ => {}
     });
 
     match err {
         Some(err) => Err(err),
-        None => serde_json::to_writer(std::io::stdout(), &book)
-            .map_err(|e| io::Error::new(io::ErrorKind::Other, e)),
+        None => serde_json::to_writer(std::io::stdout(), &book).map_err(io::Error::other),
     }
 }
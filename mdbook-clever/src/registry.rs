@@ -0,0 +1,1949 @@
+use std::{
+    collections::BTreeMap,
+    io,
+    path::{Path, PathBuf},
+};
+
+use mdbook::{BookItem, book::Book, utils::normalize_id};
+use pulldown_cmark::{CodeBlockKind, Event, Options, Parser, Tag, TagEnd};
+
+use crate::{
+    asm::{self, AsmBlock},
+    config::{Config, Level, TableNumbering},
+    data,
+    helpers::{self, TagExpander},
+    spec_lang::{
+        self,
+        ast::{BitRange, Elem, Encoding, EncodingField, Exception, Item, Register, Table},
+    },
+};
+
+/// The heading of tables, such as `T:R1`, that list registers
+const REGISTER_TABLE_HEADING: [&str; 4] = ["Number", "Name", "Alias Names", "Properties"];
+
+/// The heading of the status table at the top of each document
+const STATUS_TABLE_HEADING: &str = "Document Status";
+
+/// The heading of tables, such as "ALU 2 Op", that give the properties of each operand of a group of instructions
+const OP_GROUP_TABLE_HEADING: [&str; 3] = ["Pos", "Operand", "Properties"];
+
+/// The encoding of the first word of every instruction, and the field within it that holds the control bits
+pub const INSTRUCTION_ENCODING: &str = "E:insn";
+pub const CONTROL_FIELD: &str = "h";
+
+/// The labels of the tables in `D-toolchain` listing inline assembly register groups and operand modifiers
+const REG_GROUP_TABLE: &str = "toolchain:reggroups";
+const MODIFIER_TABLE: &str = "toolchain:modifiers";
+
+/// The heading of the column of nested tables that list the exceptions an operand or instruction can raise
+const EXCEPTION_CONDITIONS_HEADING: &str = "Exception Conditions";
+
+/// The first two columns of tables, such as `T:1`, that define register, operand or instruction properties
+const PROPERTY_TABLE_HEADING: [&str; 2] = ["Property", "Description"];
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Cell {
+    pub text: String,
+    pub link: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Instruction {
+    pub opcode: u16,
+    pub mnemonic: String,
+    pub aliases: Vec<String>,
+    pub extension: Option<String>,
+    pub operands: Option<u32>,
+    /// The `Control` pattern, such as `l00f`, where each letter is a bit of the named control parameter
+    pub control: Option<String>,
+    /// The destination of the `Op Group` row's link, such as `#alu-2-op`
+    pub op_group: Option<String>,
+    pub properties: Vec<String>,
+    /// The properties given for individual operands by `Op<n> Props` rows, with the 1-based operand number
+    pub operand_properties: Vec<(u32, Vec<String>)>,
+    pub chapter: PathBuf,
+    pub anchor: Option<String>,
+}
+
+impl Instruction {
+    pub fn extension_name(&self) -> &str {
+        self.extension.as_deref().unwrap_or("main")
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RegisterDef {
+    pub name: String,
+    pub number: u32,
+    pub aliases: Vec<String>,
+    pub properties: Vec<String>,
+    pub fields: Vec<(String, BitRange)>,
+    /// The fields whose bits are not yet specified
+    pub unspecified_fields: Vec<String>,
+    pub chapter: PathBuf,
+}
+
+impl RegisterDef {
+    fn from_ast(reg: &Register, chapter: &Path) -> Self {
+        Self {
+            name: reg.name.body.0.to_string(),
+            number: reg.number.body,
+            aliases: reg.aliases.iter().map(|a| a.body.0.to_string()).collect(),
+            properties: reg
+                .properties
+                .iter()
+                .map(|p| p.body.0.to_string())
+                .collect(),
+            fields: reg
+                .fields
+                .iter()
+                .filter_map(|f| match &f.body {
+                    EncodingField::Field(name, range) => {
+                        Some((name.body.0.to_string(), range.body))
+                    }
+                    EncodingField::Fixed(..) => None,
+                })
+                .collect(),
+            unspecified_fields: reg
+                .unspecified_fields
+                .iter()
+                .map(|f| f.body.0.to_string())
+                .collect(),
+            chapter: chapter.to_path_buf(),
+        }
+    }
+
+    pub fn anchor(&self) -> String {
+        Register::anchor(&self.name)
+    }
+
+    /// The name and every alias of the register
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        core::iter::once(&*self.name).chain(self.aliases.iter().map(|a| &**a))
+    }
+
+    pub fn field(&self, name: &str) -> Option<BitRange> {
+        self.fields
+            .iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, range)| *range)
+    }
+
+    /// Whether the register has the field `name`, whether or not its bits are specified
+    pub fn has_field(&self, name: &str) -> bool {
+        self.field(name).is_some() || self.unspecified_fields.iter().any(|f| f == name)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExtensionDef {
+    pub name: String,
+    /// The version that introduced the extension, from the version chapter it is listed under
+    pub version: Option<String>,
+    /// The extensions listed in the `REQUIRES` row of the extension's status table, without the `X-` prefix
+    pub requires: Vec<String>,
+    pub chapter: PathBuf,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EncodingDef {
+    /// The label of the encoding, such as `E:insn`
+    pub label: String,
+    pub width: u32,
+    pub fields: Vec<(String, BitRange)>,
+    /// Bits with a fixed value, written from most to least significant
+    pub fixed: Vec<(String, BitRange)>,
+    pub chapter: PathBuf,
+}
+
+impl EncodingDef {
+    fn from_ast(enc: &Encoding, chapter: &Path) -> Self {
+        let mut def = Self {
+            label: ipath_text(&enc.label.body.0),
+            width: enc.width.body,
+            fields: Vec::new(),
+            fixed: Vec::new(),
+            chapter: chapter.to_path_buf(),
+        };
+
+        for field in &enc.fields {
+            match &field.body {
+                EncodingField::Field(name, range) => {
+                    def.fields.push((name.body.0.to_string(), range.body))
+                }
+                EncodingField::Fixed(bits, range) => {
+                    def.fixed.push((bits.body.to_string(), range.body))
+                }
+            }
+        }
+
+        def
+    }
+
+    pub fn field(&self, name: &str) -> Option<BitRange> {
+        self.fields
+            .iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, range)| *range)
+    }
+}
+
+/// An inline assembly register group from `D-toolchain`, such as `reg`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RegGroup {
+    pub name: String,
+    /// The constraint code, without quotes
+    pub constraint: String,
+    /// The kinds of primitive type supported, such as `Integer`, with the supported widths of each
+    pub types: Vec<(String, Vec<u32>)>,
+    pub registers: Vec<String>,
+    pub feature: String,
+    pub chapter: PathBuf,
+}
+
+/// An inline assembly operand modifier from `D-toolchain`, such as `l`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AsmModifier {
+    pub name: String,
+    pub groups: Vec<String>,
+    pub feature: String,
+}
+
+/// The header of an `instruction` in pseudocode, such as `instruction 0o0010(dest: Operand, src: Operand, f: bool)`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PseudocodeHeader {
+    pub opcodes: Vec<u16>,
+    /// The name and type of each parameter
+    pub params: Vec<(String, String)>,
+    pub chapter: PathBuf,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Operand {
+    pub pos: u32,
+    pub name: String,
+    pub properties: Vec<String>,
+}
+
+/// A table of operand properties shared by a group of instructions, named by the heading above it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OpGroup {
+    pub name: String,
+    pub chapter: PathBuf,
+    pub anchor: String,
+    pub operands: Vec<Operand>,
+}
+
+/// What a property applies to, determined by the heading of the section containing its defining table
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PropertyKind {
+    Register,
+    Operand,
+    Instruction,
+    Other,
+}
+
+impl PropertyKind {
+    fn from_section(heading: &str) -> Self {
+        if heading.contains("Register") {
+            Self::Register
+        } else if heading.contains("Operand") {
+            Self::Operand
+        } else if heading.contains("Instruction") {
+            Self::Instruction
+        } else {
+            Self::Other
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PropertyDef {
+    pub name: String,
+    pub kind: PropertyKind,
+    pub chapter: PathBuf,
+    pub anchor: String,
+    /// Whether `anchor` is placed on the definition row itself, which is only possible for markdown tables.
+    ///
+    /// Otherwise, `anchor` is the section containing the defining table.
+    pub row_anchor: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExceptionDef {
+    pub name: String,
+    /// The vector number, unless it is not yet assigned
+    pub vector: Option<u32>,
+    /// Markdown describing the error code pushed with the exception
+    pub code: Option<String>,
+    pub description: Option<String>,
+    pub chapter: PathBuf,
+}
+
+impl ExceptionDef {
+    fn from_ast(exception: &Exception, chapter: &Path) -> Self {
+        Self {
+            name: exception.name.body.0.to_string(),
+            vector: exception.vector.as_ref().map(|v| v.body),
+            code: exception.code.as_ref().map(|e| elem_source(&e.body)),
+            description: exception.description.as_ref().map(|e| elem_source(&e.body)),
+            chapter: chapter.to_path_buf(),
+        }
+    }
+
+    pub fn anchor(&self) -> String {
+        Exception::anchor(&self.name)
+    }
+}
+
+/// A table with a caption, which is numbered and listed in the List of Tables
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TableDef {
+    pub label: String,
+    /// Markdown for the caption, without the number
+    pub caption: String,
+    /// The number of the table, such as `3.2`, as set by `table-numbering`
+    pub number: String,
+    pub anchor: String,
+    pub chapter: PathBuf,
+    /// The name of the chapter in the summary, such as `X-main`
+    pub chapter_name: String,
+}
+
+/// A mention of an exception that must be defined, such as `raise UND` in pseudocode
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct ExceptionRef {
+    name: String,
+    chapter: PathBuf,
+    context: &'static str,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Registry {
+    pub instructions: Vec<Instruction>,
+    pub registers: Vec<RegisterDef>,
+    pub extension_defs: Vec<ExtensionDef>,
+    pub properties: Vec<PropertyDef>,
+    pub exceptions: Vec<ExceptionDef>,
+    pub op_groups: Vec<OpGroup>,
+    pub encodings: Vec<EncodingDef>,
+    pub pseudocode_headers: Vec<PseudocodeHeader>,
+    pub reg_groups: Vec<RegGroup>,
+    pub asm_modifiers: Vec<AsmModifier>,
+    /// The captioned tables, in the order they appear in the book
+    pub tables: Vec<TableDef>,
+    exception_refs: Vec<ExceptionRef>,
+    /// The assembly examples in each chapter, which are checked against the instructions and registers
+    asm_blocks: Vec<(PathBuf, AsmBlock)>,
+    /// The anchor of every heading in the book, with the chapter it is in
+    anchors: Vec<(PathBuf, String)>,
+}
+
+impl Registry {
+    pub const OPCODE_SPACE: u16 = 0o10000;
+
+    /// Collects the definitions of every chapter of `book`, and checks them at the levels given by `config`
+    pub fn collect(book: &Book, src_dir: &Path, config: &Config) -> io::Result<Self> {
+        let mut registry = Self::default();
+
+        // The chapters nested under each `versions/<version>.md` chapter, which were introduced in that version
+        let mut versions: Vec<(&Path, &str)> = Vec::new();
+
+        // The number of tables captioned so far in each top-level chapter, or in the book as a whole
+        let mut table_counts: BTreeMap<Option<u32>, usize> = BTreeMap::new();
+
+        for item in book.iter() {
+            if let BookItem::Chapter(c) = item
+                && let Some(path) = c.path.as_deref()
+            {
+                if let Some(version) = version_of(path) {
+                    versions.extend(c.sub_items.iter().filter_map(|sub| match sub {
+                        BookItem::Chapter(sub) => Some((sub.path.as_deref()?, version)),
+                        _ => None,
+                    }));
+                }
+
+                let version = versions
+                    .iter()
+                    .find(|(p, _)| *p == path)
+                    .map(|(_, version)| *version);
+
+                let dir = helpers::chapter_dir(src_dir, c);
+                let first_table = registry.tables.len();
+                registry.collect_chapter(path, version, &dir, &c.content, config)?;
+
+                let section = match config.table_numbering {
+                    TableNumbering::Chapter => c.number.as_ref().and_then(|n| n.first().copied()),
+                    TableNumbering::Book => None,
+                };
+                for table in &mut registry.tables[first_table..] {
+                    let count = table_counts.entry(section).or_default();
+                    *count += 1;
+                    table.number = match section {
+                        Some(section) => format!("{section}.{count}"),
+                        None => count.to_string(),
+                    };
+                    table.chapter_name = c.name.clone();
+                }
+            }
+        }
+
+        let checks = &config.checks;
+        checks.extensions.report(registry.check_extensions())?;
+        registry.check_property_uses(checks.property_uses)?;
+        checks.exceptions.report(registry.check_exceptions())?;
+        checks.op_groups.report(registry.check_op_groups())?;
+        checks.control_bits.report(registry.check_control_bits())?;
+        checks.asm.report(registry.check_asm_blocks())?;
+
+        registry.instructions.sort_by_key(|i| i.opcode);
+        registry.registers.sort_by_key(|r| r.number);
+
+        if let Some([a, b]) = registry
+            .registers
+            .windows(2)
+            .find(|w| w[0].number == w[1].number)
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Register number {} is assigned to both `{}` ({}) and `{}` ({})",
+                    a.number,
+                    a.name,
+                    a.chapter.display(),
+                    b.name,
+                    b.chapter.display()
+                ),
+            ));
+        }
+
+        for (idx, reg) in registry.registers.iter().enumerate() {
+            for name in reg.names() {
+                if let Some(other) = registry.registers[idx + 1..]
+                    .iter()
+                    .find(|r| r.names().any(|n| n == name))
+                {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!(
+                            "Register name `{name}` refers to both register {} ({}) and register {} ({})",
+                            reg.number,
+                            reg.chapter.display(),
+                            other.number,
+                            other.chapter.display()
+                        ),
+                    ));
+                }
+            }
+        }
+
+        if let Some([a, b]) = registry
+            .instructions
+            .windows(2)
+            .find(|w| w[0].opcode == w[1].opcode)
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Opcode {:#06o} is assigned to both `{}` ({}) and `{}` ({})",
+                    a.opcode,
+                    a.mnemonic,
+                    a.chapter.display(),
+                    b.mnemonic,
+                    b.chapter.display()
+                ),
+            ));
+        }
+
+        Ok(registry)
+    }
+
+    fn collect_chapter(
+        &mut self,
+        path: &Path,
+        version: Option<&str>,
+        dir: &Path,
+        content: &str,
+        config: &Config,
+    ) -> io::Result<()> {
+        let extension = extension_of(path);
+
+        if let Some(name) = &extension {
+            self.extension_defs.push(ExtensionDef {
+                name: name.clone(),
+                version: version.map(str::to_string),
+                requires: Vec::new(),
+                chapter: path.to_path_buf(),
+            });
+        }
+
+        let mut parser = Parser::new_ext(
+            content,
+            Options::ENABLE_TABLES
+                | Options::ENABLE_FOOTNOTES
+                | Options::ENABLE_STRIKETHROUGH
+                | Options::ENABLE_HEADING_ATTRIBUTES,
+        );
+
+        self.asm_blocks.extend(
+            asm::asm_blocks(content)
+                .into_iter()
+                .map(|block| (path.to_path_buf(), block)),
+        );
+
+        let mut anchor = None;
+        let mut heading = String::new();
+
+        while let Some(event) = parser.next() {
+            match event {
+                Event::Start(Tag::Heading { id, .. }) => {
+                    let mut text = String::new();
+                    for e in parser.by_ref() {
+                        match e {
+                            Event::Text(t) | Event::Code(t) => text.push_str(&t),
+                            Event::End(TagEnd::Heading(_)) => break,
+                            _ => {}
+                        }
+                    }
+                    let id = id
+                        .map(|id| id.to_string())
+                        .unwrap_or_else(|| normalize_id(text.trim()));
+                    self.anchors.push((path.to_path_buf(), id.clone()));
+                    anchor = Some(id);
+                    heading = text.trim().to_string();
+                }
+                Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(lang)))
+                    if config
+                        .spec_block(&lang)
+                        .is_some_and(|info| info.is_ok_and(|info| info.mode.is_rendered())) =>
+                {
+                    let file = config
+                        .spec_block(&lang)
+                        .and_then(Result::ok)
+                        .and_then(|info| info.file);
+                    let mut body = String::new();
+                    for e in parser.by_ref() {
+                        match e {
+                            Event::Text(text) => body.push_str(&text),
+                            Event::End(TagEnd::CodeBlock) => break,
+                            _ => {}
+                        }
+                    }
+
+                    let in_chapter =
+                        |e: io::Error| io::Error::new(e.kind(), format!("{}: {e}", path.display()));
+
+                    // Errors in an included file are already located in that file
+                    let source =
+                        spec_lang::Source::load(body, file, dir, path).map_err(in_chapter)?;
+                    let item = source
+                        .parse()
+                        .and_then(|mut item| {
+                            data::load_item(&mut item.body, &source.dir)?;
+                            Ok(item)
+                        })
+                        .map_err(|e| match source.file {
+                            Some(_) => e,
+                            None => in_chapter(e),
+                        })?;
+
+                    if let Item::Elem(Elem::Table(table)) = &item.body {
+                        self.collect_exception_conditions(table, path);
+                        self.collect_table(table, path)?;
+                    }
+
+                    match &item.body {
+                        Item::Exception(exception) => self
+                            .exceptions
+                            .push(ExceptionDef::from_ast(exception, path)),
+                        Item::Register(reg) => {
+                            self.add_register(RegisterDef::from_ast(reg, path), true)?
+                        }
+                        Item::Encoding(enc) => {
+                            self.encodings.push(EncodingDef::from_ast(enc, path))
+                        }
+                        Item::Elem(Elem::Table(table))
+                            if ipath_text(&table.label.body.0) == REG_GROUP_TABLE =>
+                        {
+                            self.reg_groups.extend(reg_groups_from_table(table, path)?)
+                        }
+                        Item::Elem(Elem::Table(table))
+                            if ipath_text(&table.label.body.0) == MODIFIER_TABLE =>
+                        {
+                            self.asm_modifiers
+                                .extend(modifiers_from_table(table, path)?)
+                        }
+                        Item::Elem(Elem::Table(table)) if is_register_table(table) => {
+                            for reg in registers_from_table(table, path)? {
+                                self.add_register(reg, false)?;
+                            }
+                        }
+                        Item::Elem(Elem::Table(table)) if is_property_table(table) => {
+                            let anchor = anchor.clone().unwrap_or_default();
+                            self.properties.extend(table.rows.iter().filter_map(|row| {
+                                let name = elem_text(&row.body.0.first()?.body);
+                                Some(PropertyDef {
+                                    name: name.to_string(),
+                                    kind: PropertyKind::from_section(&heading),
+                                    chapter: path.to_path_buf(),
+                                    anchor: anchor.clone(),
+                                    row_anchor: false,
+                                })
+                            }));
+                        }
+                        _ => {}
+                    }
+                }
+                Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(lang)))
+                    if lang.trim() == "clever-psuedo" =>
+                {
+                    for e in parser.by_ref() {
+                        match e {
+                            Event::Text(text) => {
+                                self.pseudocode_headers
+                                    .extend(parse_pseudocode_headers(&text, path)?);
+                                self.exception_refs
+                                    .extend(raised_exceptions(&text).map(|name| ExceptionRef {
+                                        name: name.to_string(),
+                                        chapter: path.to_path_buf(),
+                                        context: "Pseudocode raises",
+                                    }))
+                            }
+                            Event::End(TagEnd::CodeBlock) => break,
+                            _ => {}
+                        }
+                    }
+                }
+                Event::Start(Tag::Table(_)) => {
+                    let rows = collect_table(&mut parser);
+                    if extension.is_some()
+                        && let Some(requires) = parse_requires(&rows, path)?
+                    {
+                        // The definition for this chapter was pushed above
+                        let def = self.extension_defs.last_mut().unwrap();
+                        def.requires.extend(requires);
+                    }
+                    self.properties
+                        .extend(parse_properties(&rows, path, &anchor, &heading));
+                    if let Some(operands) = parse_op_group(&rows, path)? {
+                        self.op_groups.push(OpGroup {
+                            name: heading.clone(),
+                            chapter: path.to_path_buf(),
+                            anchor: anchor.clone().unwrap_or_default(),
+                            operands,
+                        });
+                    }
+                    if let Some(insn) = parse_instruction(&rows, path, &extension, &anchor)? {
+                        self.instructions.push(insn);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Adds a register, merging it with a previous definition of the same register from a register table.
+    ///
+    /// Both definitions must agree on the number, aliases and properties of the register.
+    fn add_register(&mut self, reg: RegisterDef, element: bool) -> io::Result<()> {
+        let Some(prev) = self.registers.iter_mut().find(|r| r.name == reg.name) else {
+            self.registers.push(reg);
+            return Ok(());
+        };
+
+        let mismatch = |what: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Register `{}` has a different {what} in {} than in {}",
+                    reg.name,
+                    reg.chapter.display(),
+                    prev.chapter.display()
+                ),
+            )
+        };
+
+        if prev.number != reg.number {
+            return Err(mismatch("number"));
+        }
+        if prev.aliases != reg.aliases {
+            return Err(mismatch("set of aliases"));
+        }
+        if prev.properties != reg.properties {
+            return Err(mismatch("set of properties"));
+        }
+
+        if element {
+            if !prev.fields.is_empty() || !prev.unspecified_fields.is_empty() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "Register `{}` is defined in both {} and {}",
+                        reg.name,
+                        reg.chapter.display(),
+                        prev.chapter.display()
+                    ),
+                ));
+            }
+            *prev = reg;
+        }
+
+        Ok(())
+    }
+
+    /// Checks that every prerequisite of an extension exists, was introduced no later than the extension itself, and
+    /// does not (transitively) require the extension
+    fn check_extensions(&self) -> io::Result<()> {
+        for def in &self.extension_defs {
+            for req in &def.requires {
+                let Some(prereq) = self.extension_def(req) else {
+                    return Err(invalid_data(
+                        &def.chapter,
+                        format!("X-{} requires unknown extension X-{req}", def.name),
+                    ));
+                };
+
+                if let (Some(version), Some(prereq_version)) = (&def.version, &prereq.version)
+                    && compare_versions(prereq_version, version).is_gt()
+                {
+                    return Err(invalid_data(
+                        &def.chapter,
+                        format!(
+                            "X-{} (version {version}) requires X-{req}, which was introduced later in version {prereq_version}",
+                            def.name
+                        ),
+                    ));
+                }
+            }
+        }
+
+        // Depth-first search from each extension, where `stack` is the current path of requirements
+        fn visit<'a>(
+            registry: &'a Registry,
+            def: &'a ExtensionDef,
+            stack: &mut Vec<&'a str>,
+            done: &mut Vec<&'a str>,
+        ) -> io::Result<()> {
+            if done.contains(&&*def.name) {
+                return Ok(());
+            }
+            if let Some(start) = stack.iter().position(|&n| n == def.name) {
+                let cycle = stack[start..]
+                    .iter()
+                    .chain(core::iter::once(&&*def.name))
+                    .map(|n| format!("X-{n}"))
+                    .collect::<Vec<_>>()
+                    .join(" -> ");
+                return Err(invalid_data(
+                    &def.chapter,
+                    format!("Extension requirements form a cycle: {cycle}"),
+                ));
+            }
+
+            stack.push(&def.name);
+            for req in &def.requires {
+                if let Some(prereq) = registry.extension_def(req) {
+                    visit(registry, prereq, stack, done)?;
+                }
+            }
+            stack.pop();
+            done.push(&def.name);
+
+            Ok(())
+        }
+
+        let mut done = Vec::new();
+        for def in &self.extension_defs {
+            visit(self, def, &mut Vec::new(), &mut done)?;
+        }
+
+        Ok(())
+    }
+
+    /// Collects a table if it has a caption, leaving its number and chapter name to be filled in by [`Self::collect`]
+    fn collect_table(&mut self, table: &Table, path: &Path) -> io::Result<()> {
+        let Some(caption) = table.caption_text() else {
+            return Ok(());
+        };
+        let label = ipath_text(&table.label.body.0);
+
+        if self.table(path, &label).is_some() {
+            return Err(invalid_data(
+                path,
+                format!("Table `{label}` is captioned more than once"),
+            ));
+        }
+
+        self.tables.push(TableDef {
+            label,
+            caption: caption.to_string(),
+            number: String::new(),
+            anchor: table.anchor(),
+            chapter: path.to_path_buf(),
+            chapter_name: String::new(),
+        });
+
+        Ok(())
+    }
+
+    /// Records the exceptions listed by the first column of each nested table in the `Exception Conditions` column of
+    /// `table`
+    fn collect_exception_conditions(&mut self, table: &Table, path: &Path) {
+        let Some(column) = table.heading.as_ref().and_then(|heading| {
+            heading
+                .body
+                .0
+                .iter()
+                .position(|e| elem_text(&e.body) == EXCEPTION_CONDITIONS_HEADING)
+        }) else {
+            return;
+        };
+
+        for row in &table.rows {
+            let Some(Elem::Table(conditions)) = row.body.0.get(column).map(|e| &e.body) else {
+                continue;
+            };
+
+            for condition in &conditions.rows {
+                let Some(first) = condition.body.0.first() else {
+                    continue;
+                };
+                let text = elem_text(&first.body);
+                self.exception_refs.push(ExceptionRef {
+                    name: parse_exception_mention(text)
+                        .map_or(text, |(name, _)| name)
+                        .to_string(),
+                    chapter: path.to_path_buf(),
+                    context: "Exception condition table lists",
+                });
+            }
+        }
+    }
+
+    /// Checks that exception mnemonics and assigned vectors are unique, and that every exception raised by pseudocode or
+    /// listed in an exception condition table is defined
+    fn check_exceptions(&mut self) -> io::Result<()> {
+        // Exceptions without a vector go last
+        self.exceptions
+            .sort_by_key(|e| (e.vector.is_none(), e.vector));
+
+        if let Some([a, b]) = self
+            .exceptions
+            .windows(2)
+            .find(|w| w[0].vector.is_some() && w[0].vector == w[1].vector)
+        {
+            return Err(invalid_data(
+                &b.chapter,
+                format!(
+                    "Exception vector {} is assigned to both `{}` ({}) and `{}`",
+                    a.vector.unwrap_or_default(),
+                    a.name,
+                    a.chapter.display(),
+                    b.name
+                ),
+            ));
+        }
+
+        for (idx, exception) in self.exceptions.iter().enumerate() {
+            if let Some(other) = self.exceptions[idx + 1..]
+                .iter()
+                .find(|e| e.name == exception.name)
+            {
+                return Err(invalid_data(
+                    &other.chapter,
+                    format!(
+                        "Exception `{}` is already defined in {}",
+                        exception.name,
+                        exception.chapter.display()
+                    ),
+                ));
+            }
+        }
+
+        for r in &self.exception_refs {
+            if self.exception(&r.name).is_none() {
+                return Err(invalid_data(
+                    &r.chapter,
+                    format!("{} undefined exception `{}`", r.context, r.name),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn exception(&self, name: &str) -> Option<&ExceptionDef> {
+        self.exceptions.iter().find(|e| e.name == name)
+    }
+
+    /// Resolves a mention of an exception, such as `UND` or `PROT (0)`, to a link to its definition
+    pub fn exception_link(&self, text: &str, tag: &TagExpander) -> Option<String> {
+        let (name, _) = parse_exception_mention(text)?;
+        let exception = self.exception(name)?;
+
+        Some(tag.chapter_link(&exception.chapter, Some(&exception.anchor())))
+    }
+
+    /// The captioned table with `label` in `chapter`
+    pub fn table(&self, chapter: &Path, label: &str) -> Option<&TableDef> {
+        self.tables
+            .iter()
+            .find(|table| table.chapter == chapter && table.label == label)
+    }
+
+    /// Gives a captioned table the number it was assigned when the book was collected
+    pub fn number_table(&self, item: &mut Item, tag: &TagExpander) {
+        let (Item::Elem(Elem::Table(table)), Some(chapter)) = (item, tag.chapter()) else {
+            return;
+        };
+        if let Some(def) = self.table(chapter, &ipath_text(&table.label.body.0)) {
+            table.number = Some(def.number.clone());
+        }
+    }
+
+    /// Turns the first cell of each row of the nested tables in the `Exception Conditions` column of `item`, where it
+    /// mentions an exception such as `<!**PROT (0)**!>`, into a link to the exception's definition
+    pub fn link_exception_cells(&self, item: &mut Item, tag: &TagExpander) {
+        let Item::Elem(Elem::Table(table)) = item else {
+            return;
+        };
+        let Some(column) = table.heading.as_ref().and_then(|heading| {
+            heading
+                .body
+                .0
+                .iter()
+                .position(|e| elem_text(&e.body) == EXCEPTION_CONDITIONS_HEADING)
+        }) else {
+            return;
+        };
+
+        for row in &mut table.rows {
+            let Some(Elem::Table(conditions)) = row.body.0.get_mut(column).map(|e| &mut e.body)
+            else {
+                continue;
+            };
+
+            for condition in &mut conditions.rows {
+                let Some(first) = condition.body.0.first_mut() else {
+                    continue;
+                };
+                let (Elem::MarkdownLiteral(text) | Elem::StringLiteral(text)) = &first.body else {
+                    continue;
+                };
+                if let Some(link) = self.exception_link(text, tag) {
+                    first.body = Elem::MarkdownLiteral(format!("[{}]({link})", text.trim()).into());
+                }
+            }
+        }
+    }
+
+    /// Checks that each instruction's `Op Group` links to an operand table with one row for each of its operands, and
+    /// that its `Op<n> Props` overrides name one of those operands
+    fn check_op_groups(&self) -> io::Result<()> {
+        for insn in &self.instructions {
+            let describe = || format!("Instruction `{}` ({:#06o})", insn.mnemonic, insn.opcode);
+
+            for (op, _) in &insn.operand_properties {
+                if !insn.operands.is_some_and(|count| (1..=count).contains(op)) {
+                    return Err(invalid_data(
+                        &insn.chapter,
+                        format!(
+                            "{} overrides the properties of operand {op}, but has {} operands",
+                            describe(),
+                            insn.operands.unwrap_or(0)
+                        ),
+                    ));
+                }
+            }
+
+            let Some(link) = &insn.op_group else {
+                continue;
+            };
+
+            let (chapter, anchor) = resolve_link(&insn.chapter, link);
+
+            let Some(group) = self
+                .op_groups
+                .iter()
+                .find(|g| g.chapter == chapter && g.anchor == anchor)
+            else {
+                let exists = self
+                    .anchors
+                    .iter()
+                    .any(|(c, a)| *c == chapter && *a == anchor);
+                let msg = if exists {
+                    format!(
+                        "{}'s Op Group links to `{link}`, which is not an operand table",
+                        describe()
+                    )
+                } else {
+                    format!(
+                        "{}'s Op Group links to `{link}`, which does not exist",
+                        describe()
+                    )
+                };
+                return Err(invalid_data(&insn.chapter, msg));
+            };
+
+            let operands = insn.operands.unwrap_or(0);
+            if group.operands.len() != operands as usize {
+                return Err(invalid_data(
+                    &insn.chapter,
+                    format!(
+                        "{} has {operands} operands, but its Op Group `{}` ({}#{}) has {} positions",
+                        describe(),
+                        group.name,
+                        group.chapter.display(),
+                        group.anchor,
+                        group.operands.len()
+                    ),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The operand table linked from an instruction's `Op Group` row
+    pub fn op_group(&self, insn: &Instruction) -> Option<&OpGroup> {
+        let (chapter, anchor) = resolve_link(&insn.chapter, insn.op_group.as_deref()?);
+        self.op_groups
+            .iter()
+            .find(|g| g.chapter == chapter && g.anchor == anchor)
+    }
+
+    /// Whether `name` is a register, an alias of one, or a member of an inline assembly register group
+    pub fn is_register_name(&self, name: &str) -> bool {
+        self.register(name).is_some()
+            || self
+                .reg_groups
+                .iter()
+                .any(|g| g.registers.iter().any(|r| r == name))
+    }
+
+    /// The instructions with a mnemonic or alias, such as `mov` or `und`
+    pub fn instructions_named<'a>(
+        &'a self,
+        name: &'a str,
+    ) -> impl Iterator<Item = &'a Instruction> {
+        self.instructions
+            .iter()
+            .filter(move |i| i.mnemonic == name || i.aliases.iter().any(|a| a == name))
+    }
+
+    /// Checks that each instruction in a `clever-asm` block names an instruction, and that any operand named like a
+    /// register, such as `r16`, is a register that is defined
+    fn check_asm_blocks(&self) -> io::Result<()> {
+        for (chapter, block) in &self.asm_blocks {
+            for stmt in asm::statements(&block.text) {
+                let line = block.line + stmt.line - 1;
+                let located = |msg: String| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("{}:{line}: {msg}", chapter.display()),
+                    )
+                };
+
+                if self.instructions_named(stmt.mnemonic).next().is_none() {
+                    return Err(located(format!("Unknown mnemonic `{}`", stmt.mnemonic)));
+                }
+
+                for name in &stmt.operands {
+                    if asm::looks_like_register(name) && !self.is_register_name(name) {
+                        return Err(located(format!("Unknown register `{name}`")));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn encoding(&self, label: &str) -> Option<&EncodingDef> {
+        self.encodings.iter().find(|e| e.label == label)
+    }
+
+    /// Checks that each instruction's `Control` pattern is as wide as the control field of the instruction encoding,
+    /// and that its letters are exactly the non-operand parameters of the instruction's pseudocode header, with one
+    /// letter for each bit of the parameter
+    fn check_control_bits(&self) -> io::Result<()> {
+        let control_width = self
+            .encoding(INSTRUCTION_ENCODING)
+            .and_then(|enc| enc.field(CONTROL_FIELD))
+            .map(|range| range.width());
+
+        for insn in &self.instructions {
+            let Some(control) = &insn.control else {
+                continue;
+            };
+            let describe = || format!("Instruction `{}` ({:#06o})", insn.mnemonic, insn.opcode);
+
+            if let Some(width) = control_width
+                && control.chars().count() != width as usize
+            {
+                return Err(invalid_data(
+                    &insn.chapter,
+                    format!(
+                        "{} has Control `{control}`, but the `{CONTROL_FIELD}` field of {INSTRUCTION_ENCODING} is {width} bits wide",
+                        describe()
+                    ),
+                ));
+            }
+
+            let Some(header) = self
+                .pseudocode_headers
+                .iter()
+                .find(|h| h.opcodes.contains(&insn.opcode))
+            else {
+                continue;
+            };
+
+            let mut letters: Vec<(char, u32)> = Vec::new();
+            for c in control.chars().filter(|c| !matches!(c, '0' | '1')) {
+                match letters.iter_mut().find(|(l, _)| *l == c) {
+                    Some((_, count)) => *count += 1,
+                    None => letters.push((c, 1)),
+                }
+            }
+
+            for (letter, count) in &letters {
+                let Some((_, ty)) = header
+                    .params
+                    .iter()
+                    .find(|(name, _)| name.len() == 1 && name.starts_with(*letter))
+                else {
+                    return Err(invalid_data(
+                        &insn.chapter,
+                        format!(
+                            "{} has Control `{control}`, but the pseudocode in {} has no parameter `{letter}`",
+                            describe(),
+                            header.chapter.display()
+                        ),
+                    ));
+                };
+
+                if let Some(width) = type_width(ty)
+                    && width != *count
+                {
+                    return Err(invalid_data(
+                        &insn.chapter,
+                        format!(
+                            "{} has {count} bits of `{letter}` in Control `{control}`, but the pseudocode in {} declares `{letter}: {ty}`",
+                            describe(),
+                            header.chapter.display()
+                        ),
+                    ));
+                }
+            }
+
+            for (name, ty) in &header.params {
+                if ty == "Operand" {
+                    continue;
+                }
+                if !letters
+                    .iter()
+                    .any(|(l, _)| name.len() == 1 && name.starts_with(*l))
+                {
+                    return Err(invalid_data(
+                        &header.chapter,
+                        format!(
+                            "Pseudocode parameter `{name}: {ty}` of {} does not appear in its Control `{control}` ({})",
+                            describe(),
+                            insn.chapter.display()
+                        ),
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reports each property named by an instruction, operand or register which is not defined in a property table
+    fn check_property_uses(&self, level: Level) -> io::Result<()> {
+        let uses = self
+            .instructions
+            .iter()
+            .flat_map(|insn| {
+                insn.properties
+                    .iter()
+                    .chain(insn.operand_properties.iter().flat_map(|(_, props)| props))
+                    .map(|prop| (prop, &insn.chapter))
+            })
+            .chain(
+                self.registers
+                    .iter()
+                    .flat_map(|reg| reg.properties.iter().map(|prop| (prop, &reg.chapter))),
+            )
+            .chain(self.op_groups.iter().flat_map(|group| {
+                group
+                    .operands
+                    .iter()
+                    .flat_map(|op| &op.properties)
+                    .map(|prop| (prop, &group.chapter))
+            }));
+
+        for (prop, chapter) in uses {
+            if self.property(prop).is_none() {
+                level.report(Err(invalid_data(
+                    chapter,
+                    format!("Use of undefined property `{prop}`"),
+                )))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn property(&self, name: &str) -> Option<&PropertyDef> {
+        self.properties.iter().find(|p| p.name == name)
+    }
+
+    /// Resolves inline code naming a property, such as `GPR` or `READ`, to a link to its definition.
+    ///
+    /// A property defined by more than one table, such as `SUPER`, links to its first definition.
+    pub fn property_link(&self, code: &str, tag: &TagExpander) -> Option<String> {
+        let prop = self.property(code)?;
+
+        Some(tag.chapter_link(&prop.chapter, Some(&prop.anchor)))
+    }
+
+    /// The properties defined by rows of markdown tables in `chapter`, in order of definition
+    pub fn row_properties<'a>(
+        &'a self,
+        chapter: &'a Path,
+    ) -> impl Iterator<Item = &'a PropertyDef> {
+        self.properties
+            .iter()
+            .filter(move |p| p.row_anchor && p.chapter == chapter)
+    }
+
+    pub fn extension_def(&self, name: &str) -> Option<&ExtensionDef> {
+        self.extension_defs.iter().find(|e| e.name == name)
+    }
+
+    /// The version of the specification that introduced the extension defined by a chapter
+    pub fn chapter_version(&self, chapter: &Path) -> Option<&str> {
+        self.extension_def(&extension_of(chapter)?)?
+            .version
+            .as_deref()
+    }
+
+    /// The length of the longest chain of requirements below an extension, which is 0 for extensions with no
+    /// prerequisites.
+    ///
+    /// A cycle of requirements is only an error if the `extensions` check is, so a requirement that is already in the
+    /// chain being followed is skipped rather than followed again.
+    pub fn extension_depth(&self, def: &ExtensionDef) -> usize {
+        self.extension_depth_in(def, &mut Vec::new())
+    }
+
+    fn extension_depth_in<'a>(&'a self, def: &'a ExtensionDef, chain: &mut Vec<&'a str>) -> usize {
+        chain.push(&def.name);
+
+        let mut depth = 0;
+        for req in &def.requires {
+            if chain.contains(&req.as_str()) {
+                continue;
+            }
+            if let Some(prereq) = self.extension_def(req) {
+                depth = depth.max(self.extension_depth_in(prereq, chain) + 1);
+            }
+        }
+
+        chain.pop();
+        depth
+    }
+
+    pub fn instruction(&self, opcode: u16) -> Option<&Instruction> {
+        self.instructions
+            .binary_search_by_key(&opcode, |i| i.opcode)
+            .ok()
+            .map(|idx| &self.instructions[idx])
+    }
+
+    /// Looks up a register by its name or one of its aliases
+    pub fn register(&self, name: &str) -> Option<&RegisterDef> {
+        self.registers.iter().find(|r| r.names().any(|n| n == name))
+    }
+
+    /// Resolves a reference to a register field, such as `mode.XM` or `mode.XM=1`, to a link to the register's field
+    /// diagram
+    pub fn register_field_link(&self, code: &str, tag: &TagExpander) -> Option<String> {
+        let end = code
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '.'))
+            .unwrap_or(code.len());
+        let (reg, field) = code[..end].split_once('.')?;
+        let reg = self.register(reg)?;
+        if !reg.has_field(field) {
+            return None;
+        }
+
+        Some(tag.chapter_link(&reg.chapter, Some(&format!("{}-fields", reg.anchor()))))
+    }
+
+    /// The extensions which own at least one opcode, in order of first appearance in the opcode space
+    pub fn extensions(&self) -> Vec<&str> {
+        let mut exts = Vec::new();
+        for insn in &self.instructions {
+            let name = insn.extension_name();
+            if !exts.contains(&name) {
+                exts.push(name);
+            }
+        }
+        exts
+    }
+
+    /// Ranges of opcodes (inclusive) that are not owned by any instruction
+    pub fn free_ranges(&self) -> Vec<(u16, u16)> {
+        let mut ranges = Vec::new();
+        let mut next = 0;
+        for insn in &self.instructions {
+            if insn.opcode > next {
+                ranges.push((next, insn.opcode - 1));
+            }
+            next = insn.opcode + 1;
+        }
+        if next < Self::OPCODE_SPACE {
+            ranges.push((next, Self::OPCODE_SPACE - 1));
+        }
+        ranges
+    }
+}
+
+pub fn extension_of(path: &Path) -> Option<String> {
+    if path.parent()? != Path::new("extensions") {
+        return None;
+    }
+
+    path.file_stem()?.to_str().map(str::to_string)
+}
+
+fn ipath_text(path: &[crate::spec_lang::Spanned<crate::spec_lang::ast::Id>]) -> String {
+    path.iter()
+        .map(|id| id.body.0)
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+/// The number of bits of a control parameter type, such as `bool`, `i4` or `Reg4`
+fn type_width(ty: &str) -> Option<u32> {
+    if ty == "bool" {
+        return Some(1);
+    }
+
+    ty.strip_prefix(['i', 'u'])
+        .or_else(|| ty.strip_prefix("Reg"))
+        .and_then(|bits| bits.parse().ok())
+}
+
+/// Parses the header of each `instruction` in a block of pseudocode.
+///
+/// A header names a single opcode or a set of opcodes in braces, followed by its parameters, such as
+/// `instruction {0o0001, 0o0002}(dest: Operand, src2: Operand, l: bool, f: bool):`
+fn parse_pseudocode_headers(text: &str, path: &Path) -> io::Result<Vec<PseudocodeHeader>> {
+    let mut headers = Vec::new();
+
+    for line in text.lines() {
+        let Some(rest) = line.trim().strip_prefix("instruction ") else {
+            continue;
+        };
+        let malformed = || {
+            invalid_data(
+                path,
+                format!("Malformed pseudocode header `{}`", line.trim()),
+            )
+        };
+
+        let (opcodes, rest) = rest.split_once('(').ok_or_else(malformed)?;
+        let (params, _) = rest.split_once(')').ok_or_else(malformed)?;
+
+        let opcodes = opcodes
+            .trim()
+            .trim_start_matches('{')
+            .trim_end_matches('}')
+            .split(',')
+            .map(|op| parse_opcode(op.trim()).ok_or_else(malformed))
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let params = params
+            .split(',')
+            .filter(|p| !p.trim().is_empty())
+            .map(|p| {
+                let (name, ty) = p.split_once(':').ok_or_else(malformed)?;
+                Ok((name.trim().to_string(), ty.trim().to_string()))
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        headers.push(PseudocodeHeader {
+            opcodes,
+            params,
+            chapter: path.to_path_buf(),
+        });
+    }
+
+    Ok(headers)
+}
+
+/// Resolves a link from `chapter`, such as `#alu-2-op` or `main.md#alu-2-op`, to the chapter and anchor it refers to
+fn resolve_link(chapter: &Path, link: &str) -> (PathBuf, String) {
+    let (path, anchor) = link.split_once('#').unwrap_or((link, ""));
+
+    let chapter = if path.is_empty() {
+        chapter.to_path_buf()
+    } else {
+        let mut resolved = chapter.parent().map(Path::to_path_buf).unwrap_or_default();
+        for component in Path::new(path).components() {
+            match component {
+                std::path::Component::ParentDir => {
+                    resolved.pop();
+                }
+                std::path::Component::CurDir => {}
+                component => resolved.push(component),
+            }
+        }
+        resolved
+    };
+
+    (chapter, anchor.to_string())
+}
+
+/// The markdown source of a string or markdown cell
+fn elem_source(elem: &Elem) -> String {
+    match elem {
+        Elem::StringLiteral(st) | Elem::MarkdownLiteral(st) => st.trim().to_string(),
+        Elem::Table(_) => String::new(),
+    }
+}
+
+/// Parses a mention of an exception, such as `UND`, `**PF**` or `PROT (0)`, into the mnemonic and the error code, if
+/// any
+pub fn parse_exception_mention(text: &str) -> Option<(&str, Option<&str>)> {
+    let text = text
+        .trim()
+        .trim_matches('*')
+        .trim()
+        .trim_matches('`')
+        .trim();
+
+    let (name, code) = match text.split_once(' ') {
+        Some((name, code)) => (
+            name,
+            Some(code.trim().strip_prefix('(')?.strip_suffix(')')?.trim()),
+        ),
+        None => (text, None),
+    };
+
+    let is_mnemonic = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit());
+
+    is_mnemonic.then_some((name, code))
+}
+
+/// The exceptions named by each `raise <exception>` statement in pseudocode
+fn raised_exceptions(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .filter(|word| !word.is_empty())
+        .scan(false, |after_raise, word| {
+            let raised = core::mem::replace(after_raise, word == "raise");
+            Some(raised.then_some(word))
+        })
+        .flatten()
+}
+
+/// The version named by a `versions/<version>.md` chapter, where `<version>` is a dotted version number such as `1.0`.
+///
+/// Other chapters in `versions`, such as `versions/dependencies.md`, are not versions.
+pub fn version_of(path: &Path) -> Option<&str> {
+    if path.parent()? != Path::new("versions") || path.extension()? != "md" {
+        return None;
+    }
+
+    let version = path.file_stem()?.to_str()?;
+    version
+        .split('.')
+        .all(|c| !c.is_empty() && c.bytes().all(|b| b.is_ascii_digit()))
+        .then_some(version)
+}
+
+/// Orders dotted version numbers, such as `1.0` and `1.10`, component by component
+pub fn compare_versions(a: &str, b: &str) -> core::cmp::Ordering {
+    let components = |v: &str| {
+        v.split('.')
+            .map(|c| c.parse::<u64>().unwrap_or(0))
+            .collect::<Vec<_>>()
+    };
+
+    components(a).cmp(&components(b))
+}
+
+fn is_property_table(table: &Table) -> bool {
+    table.heading.as_ref().is_some_and(|heading| {
+        heading
+            .body
+            .0
+            .iter()
+            .map(|e| elem_text(&e.body))
+            .take(PROPERTY_TABLE_HEADING.len())
+            .eq(PROPERTY_TABLE_HEADING)
+    })
+}
+
+fn is_register_table(table: &Table) -> bool {
+    table.heading.as_ref().is_some_and(|heading| {
+        heading
+            .body
+            .0
+            .iter()
+            .map(|e| elem_text(&e.body))
+            .eq(REGISTER_TABLE_HEADING)
+    })
+}
+
+/// The text of a string or markdown cell, without any inline code markers
+fn elem_text<'a>(elem: &'a Elem) -> &'a str {
+    match elem {
+        Elem::StringLiteral(st) | Elem::MarkdownLiteral(st) => st.trim().trim_matches('`'),
+        Elem::Table(_) => "",
+    }
+}
+
+/// Splits a markdown list or a comma-separated list of inline code spans into its items
+fn markdown_list(elem: &Elem) -> Vec<String> {
+    let text = match elem {
+        Elem::StringLiteral(st) | Elem::MarkdownLiteral(st) => st,
+        Elem::Table(_) => return Vec::new(),
+    };
+
+    text.split(['\n', ','])
+        .map(|item| {
+            item.trim()
+                .trim_start_matches(['*', '-'])
+                .trim()
+                .trim_matches('`')
+        })
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Expands a register range, such as `r[0-15]` or `v[0-15]l`, into the names of each register
+fn expand_register_range(pattern: &str) -> Option<Vec<String>> {
+    let Some((prefix, rest)) = pattern.split_once('[') else {
+        return Some(vec![pattern.to_string()]);
+    };
+    let (range, suffix) = rest.split_once(']')?;
+    let (lo, hi) = range.split_once('-')?;
+    let (lo, hi) = (
+        lo.trim().parse::<u32>().ok()?,
+        hi.trim().parse::<u32>().ok()?,
+    );
+
+    Some((lo..=hi).map(|n| format!("{prefix}{n}{suffix}")).collect())
+}
+
+/// Parses a list of register ranges, where each item may exclude registers, such as `` `r[0-15]` except `r7` ``
+fn register_list(elem: &Elem, path: &Path) -> io::Result<Vec<String>> {
+    let text = match elem {
+        Elem::StringLiteral(st) | Elem::MarkdownLiteral(st) => st,
+        Elem::Table(_) => return Ok(Vec::new()),
+    };
+
+    let mut registers = Vec::new();
+    for item in text.lines() {
+        let item = item.trim().trim_start_matches(['*', '-']).trim();
+        if item.is_empty() {
+            continue;
+        }
+        let (included, excluded) = item.split_once(" except ").unwrap_or((item, ""));
+        let expand = |list: &str| -> io::Result<Vec<String>> {
+            let mut names = Vec::new();
+            for pattern in list.split(',').map(|p| p.trim().trim_matches('`')) {
+                if pattern.is_empty() {
+                    continue;
+                }
+                names.extend(expand_register_range(pattern).ok_or_else(|| {
+                    invalid_data(path, format!("Invalid register range `{pattern}`"))
+                })?);
+            }
+            Ok(names)
+        };
+        let excluded = expand(excluded)?;
+        registers.extend(
+            expand(included)?
+                .into_iter()
+                .filter(|r| !excluded.contains(r)),
+        );
+    }
+
+    Ok(registers)
+}
+
+fn reg_groups_from_table(table: &Table, path: &Path) -> io::Result<Vec<RegGroup>> {
+    table
+        .rows
+        .iter()
+        .map(|row| match &*row.body.0 {
+            [name, constraint, types, registers, feature] => {
+                let types = match &types.body {
+                    Elem::Table(types) => types
+                        .rows
+                        .iter()
+                        .filter_map(|row| match &*row.body.0 {
+                            [kind, widths] => Some((
+                                elem_text(&kind.body).to_string(),
+                                markdown_list(&widths.body)
+                                    .iter()
+                                    .filter_map(|w| w.parse().ok())
+                                    .collect(),
+                            )),
+                            _ => None,
+                        })
+                        .collect(),
+                    _ => Vec::new(),
+                };
+
+                Ok(RegGroup {
+                    name: elem_text(&name.body).to_string(),
+                    constraint: elem_text(&constraint.body).trim_matches('"').to_string(),
+                    types,
+                    registers: register_list(&registers.body, path)?,
+                    feature: elem_text(&feature.body).to_string(),
+                    chapter: path.to_path_buf(),
+                })
+            }
+            _ => Err(invalid_data(
+                path,
+                format!(
+                    "Register group row at {}:{} does not have 5 columns",
+                    row.span.start, row.span.end
+                ),
+            )),
+        })
+        .collect()
+}
+
+fn modifiers_from_table(table: &Table, path: &Path) -> io::Result<Vec<AsmModifier>> {
+    table
+        .rows
+        .iter()
+        .map(|row| match &*row.body.0 {
+            [name, groups, _, feature] => Ok(AsmModifier {
+                name: elem_text(&name.body).to_string(),
+                groups: markdown_list(&groups.body),
+                feature: elem_text(&feature.body).to_string(),
+            }),
+            _ => Err(invalid_data(
+                path,
+                format!(
+                    "Modifier row at {}:{} does not have 4 columns",
+                    row.span.start, row.span.end
+                ),
+            )),
+        })
+        .collect()
+}
+
+fn registers_from_table(table: &Table, path: &Path) -> io::Result<Vec<RegisterDef>> {
+    table
+        .rows
+        .iter()
+        .map(|row| match &*row.body.0 {
+            [number, name, aliases, properties] => {
+                let number = elem_text(&number.body);
+                let number = spec_lang::parse::parse_int_literal(number)
+                    .and_then(|n| u32::try_from(n).ok())
+                    .ok_or_else(|| {
+                        invalid_data(path, format!("Invalid register number `{number}`"))
+                    })?;
+
+                Ok(RegisterDef {
+                    name: elem_text(&name.body).to_string(),
+                    number,
+                    aliases: markdown_list(&aliases.body),
+                    properties: markdown_list(&properties.body),
+                    fields: Vec::new(),
+                    unspecified_fields: Vec::new(),
+                    chapter: path.to_path_buf(),
+                })
+            }
+            _ => Err(invalid_data(
+                path,
+                format!(
+                    "Register table row at {}:{} does not have {} columns",
+                    row.span.start,
+                    row.span.end,
+                    REGISTER_TABLE_HEADING.len()
+                ),
+            )),
+        })
+        .collect()
+}
+
+pub fn collect_table<'a>(parser: &mut impl Iterator<Item = Event<'a>>) -> Vec<Vec<Cell>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut cell = Cell::default();
+
+    for event in parser {
+        match event {
+            Event::Text(t) | Event::Code(t) => cell.text.push_str(&t),
+            Event::Start(Tag::Link { dest_url, .. }) => cell.link = Some(dest_url.to_string()),
+            Event::End(TagEnd::TableCell) => {
+                cell.text = cell.text.trim().to_string();
+                row.push(core::mem::take(&mut cell))
+            }
+            Event::End(TagEnd::TableHead | TagEnd::TableRow) => {
+                rows.push(core::mem::take(&mut row))
+            }
+            Event::End(TagEnd::Table) => break,
+            _ => {}
+        }
+    }
+
+    rows
+}
+
+fn invalid_data(path: &Path, msg: String) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{}: {msg}", path.display()),
+    )
+}
+
+pub fn parse_opcode(text: &str) -> Option<u16> {
+    let opcode = u16::from_str_radix(text.strip_prefix("0o")?, 8).ok()?;
+
+    (opcode < Registry::OPCODE_SPACE).then_some(opcode)
+}
+
+pub fn split_list(text: &str) -> Vec<String> {
+    text.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Parses the `REQUIRES` row of a document status table, if `rows` is one.
+///
+/// Each prerequisite is an extension tag, such as `` [`X-float`] ``, and is returned without the `X-` prefix.
+fn parse_requires(rows: &[Vec<Cell>], path: &Path) -> io::Result<Option<Vec<String>>> {
+    match rows.first().and_then(|row| row.first()) {
+        Some(head) if head.text == STATUS_TABLE_HEADING => {}
+        _ => return Ok(None),
+    }
+
+    let Some(requires) = rows[1..]
+        .iter()
+        .find(|row| row.first().is_some_and(|c| c.text == "REQUIRES"))
+        .and_then(|row| row.get(1))
+    else {
+        return Ok(None);
+    };
+
+    split_list(&requires.text)
+        .iter()
+        .map(|tag| {
+            let tag = tag.trim_matches(['[', ']', '`']);
+            tag.strip_prefix("X-")
+                .map(str::to_string)
+                .ok_or_else(|| invalid_data(path, format!("`{tag}` is not an extension tag")))
+        })
+        .collect::<io::Result<_>>()
+        .map(Some)
+}
+
+/// Parses the rows of a markdown property table, if `rows` is one, with an anchor for each row within `section`
+fn parse_properties(
+    rows: &[Vec<Cell>],
+    path: &Path,
+    section: &Option<String>,
+    heading: &str,
+) -> Vec<PropertyDef> {
+    let is_property_table = rows.first().is_some_and(|head| {
+        head.iter()
+            .map(|c| &*c.text)
+            .take(PROPERTY_TABLE_HEADING.len())
+            .eq(PROPERTY_TABLE_HEADING)
+    });
+    if !is_property_table {
+        return Vec::new();
+    }
+
+    rows[1..]
+        .iter()
+        .filter_map(|row| row.first())
+        .filter(|name| !name.text.is_empty())
+        .map(|name| PropertyDef {
+            name: name.text.clone(),
+            kind: PropertyKind::from_section(heading),
+            chapter: path.to_path_buf(),
+            anchor: match section {
+                Some(section) => format!("{section}-{}", normalize_id(&name.text)),
+                None => format!("property-{}", normalize_id(&name.text)),
+            },
+            row_anchor: true,
+        })
+        .collect()
+}
+
+/// Parses the rows of an operand table, if `rows` is one
+fn parse_op_group(rows: &[Vec<Cell>], path: &Path) -> io::Result<Option<Vec<Operand>>> {
+    let is_op_group = rows
+        .first()
+        .is_some_and(|head| head.iter().map(|c| &*c.text).eq(OP_GROUP_TABLE_HEADING));
+    if !is_op_group {
+        return Ok(None);
+    }
+
+    rows[1..]
+        .iter()
+        .enumerate()
+        .map(|(idx, row)| {
+            let [pos, name, props] = &row[..] else {
+                return Err(invalid_data(
+                    path,
+                    format!("Operand table row {} does not have 3 columns", idx + 1),
+                ));
+            };
+            let pos = pos
+                .text
+                .parse::<u32>()
+                .ok()
+                .filter(|&pos| pos as usize == idx + 1)
+                .ok_or_else(|| {
+                    invalid_data(
+                        path,
+                        format!(
+                            "Operand table row {} has position `{}`, expected `{}`",
+                            idx + 1,
+                            pos.text,
+                            idx + 1
+                        ),
+                    )
+                })?;
+
+            Ok(Operand {
+                pos,
+                name: name.text.clone(),
+                properties: property_list(&props.text),
+            })
+        })
+        .collect::<io::Result<_>>()
+        .map(Some)
+}
+
+/// Splits a list of properties, where `NONE` is an empty list
+fn property_list(text: &str) -> Vec<String> {
+    if text == "NONE" {
+        return Vec::new();
+    }
+
+    split_list(text)
+}
+
+fn parse_instruction(
+    rows: &[Vec<Cell>],
+    path: &Path,
+    extension: &Option<String>,
+    anchor: &Option<String>,
+) -> io::Result<Option<Instruction>> {
+    match rows.first().map(Vec::as_slice) {
+        Some([prop, def]) if prop.text == "Property" && def.text == "Definition" => {}
+        _ => return Ok(None),
+    }
+
+    let field = |name: &str| {
+        rows[1..]
+            .iter()
+            .find(|row| row.first().is_some_and(|c| c.text == name))
+            .and_then(|row| row.get(1))
+    };
+
+    let Some(opcode) = field("Opcode") else {
+        return Ok(None);
+    };
+
+    let opcode = parse_opcode(&opcode.text)
+        .ok_or_else(|| invalid_data(path, format!("Invalid opcode `{}`", opcode.text)))?;
+
+    let mnemonic = field("Mnemonic")
+        .map(|c| c.text.clone())
+        .ok_or_else(|| invalid_data(path, format!("Instruction {opcode:#06o} has no mnemonic")))?;
+
+    let aliases = field("Aliases")
+        .map(|c| split_list(&c.text))
+        .unwrap_or_default();
+
+    let operands = field("Operands")
+        .map(|c| {
+            c.text.parse().map_err(|_| {
+                invalid_data(
+                    path,
+                    format!(
+                        "Instruction {opcode:#06o} has invalid operand count `{}`",
+                        c.text
+                    ),
+                )
+            })
+        })
+        .transpose()?;
+
+    let control = field("Control").map(|c| c.text.clone());
+
+    let op_group = field("Op Group").and_then(|c| c.link.clone());
+
+    let properties = field("Properties")
+        .map(|c| property_list(&c.text))
+        .unwrap_or_default();
+
+    let operand_properties = rows[1..]
+        .iter()
+        .filter_map(|row| {
+            let [name, props, ..] = &row[..] else {
+                return None;
+            };
+            let op = name
+                .text
+                .strip_prefix("Op")?
+                .strip_suffix(" Props")?
+                .parse()
+                .ok()?;
+            Some((op, property_list(&props.text)))
+        })
+        .collect();
+
+    Ok(Some(Instruction {
+        opcode,
+        mnemonic,
+        aliases,
+        extension: extension.clone(),
+        operands,
+        control,
+        op_group,
+        properties,
+        operand_properties,
+        chapter: path.to_path_buf(),
+        anchor: anchor.clone(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version_of_requires_a_version_number() {
+        assert_eq!(version_of(Path::new("versions/1.0.md")), Some("1.0"));
+        assert_eq!(version_of(Path::new("versions/1.10.md")), Some("1.10"));
+        assert_eq!(version_of(Path::new("versions/dependencies.md")), None);
+        assert_eq!(version_of(Path::new("versions/1..md")), None);
+        assert_eq!(version_of(Path::new("versions/1.0.toml")), None);
+        assert_eq!(version_of(Path::new("extensions/1.0.md")), None);
+    }
+}
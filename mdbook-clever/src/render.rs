@@ -0,0 +1,31 @@
+use std::io;
+
+use crate::{generate::Generator, spec_lang::ast::Item};
+
+pub mod markdown;
+mod xhtml;
+
+pub use markdown::MarkdownBackend;
+pub use xhtml::XhtmlBackend;
+
+/// Writes the contents of `clever-spec,render` blocks and `{{#clever-...}}` directives into a chapter, in a form
+/// suited to the renderer the book is being built for
+pub trait Backend {
+    fn write_item(&self, item: &Item, out: &mut String) -> io::Result<()>;
+
+    /// Writes the generated content for the directive `{{#clever-<directive>}}`
+    fn write_directive(
+        &self,
+        generator: &Generator,
+        directive: &str,
+        out: &mut String,
+    ) -> io::Result<()>;
+}
+
+/// The backend for a renderer, such as `html` or `markdown`. Renderers without a dedicated backend get XHTML.
+pub fn backend(renderer: &str) -> Box<dyn Backend> {
+    match renderer {
+        "markdown" => Box::new(MarkdownBackend),
+        _ => Box::new(XhtmlBackend),
+    }
+}
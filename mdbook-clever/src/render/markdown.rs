@@ -0,0 +1,263 @@
+use std::io;
+
+use super::Backend;
+use crate::{
+    generate::Generator,
+    spec_lang::ast::{
+        BitRange, Elem, Encoding, EncodingField, Exception, IPath, Item, Register, Table,
+    },
+};
+
+/// Writes pipe tables, for the `markdown` renderer.
+///
+/// Pipe tables cannot be nested, so a table in a cell is written after the table that contains it, and the cell refers
+/// to it by its label.
+pub struct MarkdownBackend;
+
+fn label_text(label: &IPath) -> String {
+    label
+        .0
+        .iter()
+        .map(|id| id.body.0)
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+fn bit_range_text(range: BitRange) -> String {
+    if range.hi == range.lo {
+        range.hi.to_string()
+    } else {
+        format!("{}:{}", range.hi, range.lo)
+    }
+}
+
+/// Collapses markdown onto a single line for a table cell, turning the items of a list into bullets
+pub fn inline_markdown(text: &str) -> String {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(
+            |line| match line.strip_prefix("* ").or_else(|| line.strip_prefix("- ")) {
+                Some(item) => format!("• {item}"),
+                None => line.to_string(),
+            },
+        )
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub fn code(text: &str) -> String {
+    format!("`{text}`")
+}
+
+pub fn write_pipe_table(out: &mut String, head: &[String], rows: &[Vec<String>]) {
+    let width = rows
+        .iter()
+        .map(Vec::len)
+        .chain([head.len()])
+        .max()
+        .unwrap_or(0);
+
+    let mut write_row = |cells: &[String]| {
+        out.push('|');
+        for idx in 0..width {
+            let cell = cells.get(idx).map_or("", |cell| cell);
+            out.push(' ');
+            out.push_str(&cell.replace('|', "\\|"));
+            out.push_str(" |");
+        }
+        out.push('\n');
+    };
+
+    write_row(head);
+    write_row(&vec!["---".to_string(); width]);
+    for row in rows {
+        write_row(row);
+    }
+}
+
+/// The text of a cell, pushing any nested table onto `nested` to be written after the current one
+fn cell_text<'a, 'src>(elem: &'a Elem<'src>, nested: &mut Vec<&'a Table<'src>>) -> String {
+    match elem {
+        Elem::Table(table) => {
+            nested.push(table);
+            format!("See **{}**", label_text(&table.label.body))
+        }
+        Elem::MarkdownLiteral(text) => inline_markdown(text),
+        Elem::StringLiteral(text) => text.trim().to_string(),
+    }
+}
+
+fn write_table(out: &mut String, table: &Table) {
+    let mut nested = Vec::new();
+
+    // Pipe tables have no caption, so it is written in bold above the table
+    if let Some(caption) = &table.caption {
+        out.push_str(&format!(
+            "<a id=\"{}\"></a>**{}{}**\n\n",
+            table.anchor(),
+            table.number_prefix(),
+            cell_text(&caption.body, &mut nested)
+        ));
+    }
+
+    let head = table
+        .heading
+        .iter()
+        .flat_map(|row| &row.body.0)
+        .map(|cell| cell_text(&cell.body, &mut nested))
+        .collect::<Vec<_>>();
+    let rows = table
+        .rows
+        .iter()
+        .map(|row| {
+            row.body
+                .0
+                .iter()
+                .map(|cell| cell_text(&cell.body, &mut nested))
+                .collect()
+        })
+        .collect::<Vec<_>>();
+
+    write_pipe_table(out, &head, &rows);
+
+    for table in nested {
+        out.push_str(&format!("\n**{}**\n\n", label_text(&table.label.body)));
+        write_table(out, table);
+    }
+}
+
+fn write_encoding(out: &mut String, enc: &Encoding) {
+    let mut fields = enc.fields.iter().map(|f| &f.body).collect::<Vec<_>>();
+    fields.sort_by_key(|f| core::cmp::Reverse(f.range().body.hi));
+
+    let head = fields
+        .iter()
+        .map(|f| bit_range_text(f.range().body))
+        .collect::<Vec<_>>();
+    let row = fields
+        .iter()
+        .map(|f| match f {
+            EncodingField::Field(name, _) => code(name.body.0),
+            EncodingField::Fixed(bits, _) => bits.body.to_string(),
+        })
+        .collect();
+
+    write_pipe_table(out, &head, &[row]);
+}
+
+fn write_register(out: &mut String, reg: &Register) {
+    let name = reg.name.body.0;
+    let aliases = reg
+        .aliases
+        .iter()
+        .map(|alias| code(alias.body.0))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let properties = reg
+        .properties
+        .iter()
+        .map(|prop| prop.body.0)
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    write_pipe_table(
+        out,
+        &["Number", "Name", "Alias Names", "Properties"].map(String::from),
+        &[vec![
+            code(&reg.number.body.to_string()),
+            name.to_string(),
+            aliases,
+            properties,
+        ]],
+    );
+
+    if !reg.fields.is_empty() {
+        write_field_diagram(out, reg);
+    }
+
+    if !reg.unspecified_fields.is_empty() {
+        let rows = reg
+            .unspecified_fields
+            .iter()
+            .map(|field| {
+                vec![
+                    code(&format!("{name}.{}", field.body.0)),
+                    "*not yet specified*".to_string(),
+                ]
+            })
+            .collect::<Vec<_>>();
+
+        out.push('\n');
+        write_pipe_table(out, &["Field", "Bits"].map(String::from), &rows);
+    }
+}
+
+fn write_field_diagram(out: &mut String, reg: &Register) {
+    let name = reg.name.body.0;
+    let columns = reg.field_columns();
+    let head = columns
+        .iter()
+        .map(|(range, _)| bit_range_text(*range))
+        .collect::<Vec<_>>();
+    let row = columns
+        .iter()
+        .map(|(_, field)| match field {
+            Some(EncodingField::Field(field, _)) => code(&format!("{name}.{}", field.body.0)),
+            Some(EncodingField::Fixed(bits, _)) => bits.body.to_string(),
+            None => "*reserved*".to_string(),
+        })
+        .collect();
+
+    out.push('\n');
+    write_pipe_table(out, &head, &[row]);
+}
+
+fn write_exception(out: &mut String, exception: &Exception) {
+    let mut nested = Vec::new();
+    let [code_cell, description] = [&exception.code, &exception.description]
+        .map(|elem| elem.as_ref().map(|elem| cell_text(&elem.body, &mut nested)));
+
+    write_pipe_table(
+        out,
+        &["Mnemonic", "Vector", "Error Code", "Description"].map(String::from),
+        &[vec![
+            format!("**{}**", exception.name.body.0),
+            exception
+                .vector
+                .as_ref()
+                .map_or_else(|| "*unassigned*".to_string(), |v| code(&v.body.to_string())),
+            code_cell.unwrap_or_else(|| "*unassigned*".to_string()),
+            description.unwrap_or_default(),
+        ]],
+    );
+
+    for table in nested {
+        out.push_str(&format!("\n**{}**\n\n", label_text(&table.label.body)));
+        write_table(out, table);
+    }
+}
+
+impl Backend for MarkdownBackend {
+    fn write_item(&self, item: &Item, out: &mut String) -> io::Result<()> {
+        match item {
+            Item::Elem(Elem::Table(table)) => write_table(out, table),
+            Item::Elem(Elem::MarkdownLiteral(text)) => out.push_str(text.trim()),
+            Item::Elem(Elem::StringLiteral(text)) => out.push_str(text.trim()),
+            Item::Encoding(enc) => write_encoding(out, enc),
+            Item::Register(reg) => write_register(out, reg),
+            Item::Exception(exception) => write_exception(out, exception),
+        }
+
+        Ok(())
+    }
+
+    fn write_directive(
+        &self,
+        generator: &Generator,
+        directive: &str,
+        out: &mut String,
+    ) -> io::Result<()> {
+        generator.expand_directive_markdown(directive, out)
+    }
+}
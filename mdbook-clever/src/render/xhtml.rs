@@ -0,0 +1,26 @@
+use std::io;
+
+use mdbook_fiction_tools::xhtml::xml_to_io_error;
+
+use super::Backend;
+use crate::{generate::Generator, helpers, helpers::StringAppender, spec_lang::ast::Item};
+
+/// Writes XHTML, for the `html` renderer
+pub struct XhtmlBackend;
+
+impl Backend for XhtmlBackend {
+    fn write_item(&self, item: &Item, out: &mut String) -> io::Result<()> {
+        let mut writer = StringAppender(out);
+        let mut writer = helpers::xml_writer(&mut writer);
+        item.write_xhtml(&mut writer).map_err(xml_to_io_error)
+    }
+
+    fn write_directive(
+        &self,
+        generator: &Generator,
+        directive: &str,
+        out: &mut String,
+    ) -> io::Result<()> {
+        generator.expand_directive_xhtml(directive, out)
+    }
+}
@@ -1,10 +1,13 @@
 use std::{
     borrow::{Borrow, Cow},
     hash::Hash,
+    io,
     ops::Deref,
+    path::{Path, PathBuf},
 };
 
 use logos::{Lexer, Logos, Skip, Span};
+use nom::{Finish, combinator};
 
 #[derive(Clone, Debug)]
 pub enum CowArray<'a, T> {
@@ -93,6 +96,32 @@ impl core::fmt::Display for Error {
 
 impl std::error::Error for Error {}
 
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct SemanticError {
+    pub span: Span,
+    pub message: String,
+}
+
+impl SemanticError {
+    pub fn new(span: Span, message: impl Into<String>) -> Self {
+        Self {
+            span,
+            message: message.into(),
+        }
+    }
+}
+
+impl core::fmt::Display for SemanticError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "{} at {}:{}",
+            self.message, self.span.start, self.span.end
+        ))
+    }
+}
+
+impl std::error::Error for SemanticError {}
+
 #[derive(Logos, Clone, Eq, Debug, Hash)]
 #[logos(skip r"[ \t\n\f]+")]
 #[logos(extras = Extras)]
@@ -102,10 +131,20 @@ pub enum Token<'a> {
     KwTable,
     #[token("row", priority = 3)]
     KwRow,
+    #[token("encoding", priority = 3)]
+    KwEncoding,
+    #[token("register", priority = 3)]
+    KwRegister,
+    #[token("exception", priority = 3)]
+    KwException,
     #[token(":")]
     LabelSep,
     #[token(",")]
     Comma,
+    #[token("==")]
+    EqEq,
+    #[token("!=")]
+    NotEq,
     #[token("[", lex_bracket)]
     Bracket(CowArray<'a, Spanned<Token<'a>>>),
     #[token("]", lex_group_end)]
@@ -129,8 +168,13 @@ impl<'a, 'b> PartialEq<Token<'a>> for Token<'b> {
         match (self, other) {
             (Token::KwTable, Token::KwTable) => true,
             (Token::KwRow, Token::KwRow) => true,
+            (Token::KwEncoding, Token::KwEncoding) => true,
+            (Token::KwRegister, Token::KwRegister) => true,
+            (Token::KwException, Token::KwException) => true,
             (Token::LabelSep, Token::LabelSep) => true,
             (Token::Comma, Token::Comma) => true,
+            (Token::EqEq, Token::EqEq) => true,
+            (Token::NotEq, Token::NotEq) => true,
             (Token::Bracket(c1), Token::Bracket(c2)) => c1 == c2,
             (Token::Brace(c1), Token::Brace(c2)) => c1 == c2,
             (Token::RightBracket, Token::RightBracket) => true,
@@ -145,6 +189,18 @@ impl<'a, 'b> PartialEq<Token<'a>> for Token<'b> {
 }
 
 impl<'src> Token<'src> {
+    /// The word of a token for a reserved word, such as `table`, which cannot be used as a name
+    pub const fn reserved_word(&self) -> Option<&'static str> {
+        match self {
+            Token::KwTable => Some("table"),
+            Token::KwRow => Some("row"),
+            Token::KwEncoding => Some("encoding"),
+            Token::KwRegister => Some("register"),
+            Token::KwException => Some("exception"),
+            _ => None,
+        }
+    }
+
     pub fn borrowed<'a>(&'a self) -> Token<'a>
     where
         'src: 'a,
@@ -152,8 +208,13 @@ impl<'src> Token<'src> {
         match self {
             Token::KwTable => Token::KwTable,
             Token::KwRow => Token::KwRow,
+            Token::KwEncoding => Token::KwEncoding,
+            Token::KwRegister => Token::KwRegister,
+            Token::KwException => Token::KwException,
             Token::LabelSep => Token::LabelSep,
             Token::Comma => Token::Comma,
+            Token::EqEq => Token::EqEq,
+            Token::NotEq => Token::NotEq,
             Token::Bracket(body) => Token::Bracket(CowArray::Borrowed(body)),
             Token::Brace(body) => Token::Bracket(CowArray::Borrowed(body)),
             Token::RightBracket => Token::RightBracket,
@@ -274,3 +335,204 @@ fn lex_markdown_literal<'source>(
 pub mod ast;
 
 pub mod parse;
+
+/// Lexes and parses the body of a `clever-spec` block into a single item, giving the byte offset in `body` of any
+/// error with its message
+fn parse_spanned<'src>(body: &'src str) -> Result<Spanned<ast::Item<'src>>, (usize, String)> {
+    let tokens = Token::lexer(body)
+        .spanned()
+        .map(|(r, s)| {
+            r.map(|t| Spanned {
+                body: t,
+                span: s.clone(),
+            })
+            .map_err(|e| match &e {
+                Error::UnmatchedOpen(span) | Error::UnmatchedClose(span) => {
+                    (span.start, e.to_string())
+                }
+                _ => (s.start, e.to_string()),
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    combinator::all_consuming(combinator::complete(parse::parse_item))(&*tokens)
+        .finish()
+        .map(|(_, item)| item)
+        .map_err(|e| {
+            let offset = e.primary.0.first().map_or(body.len(), |t| t.span.start);
+            (offset, format!("Parse Error: {e}"))
+        })
+}
+
+/// The 1-based line of `text` that the byte `offset` is on
+pub fn line_of(text: &str, offset: usize) -> usize {
+    text[..offset.min(text.len())].matches('\n').count() + 1
+}
+
+/// The source of a block written in the specification language, which is either its body or a file it includes
+pub struct Source {
+    pub text: String,
+    /// The file included by the block's `file=` option, relative to the book's `src` directory
+    pub file: Option<PathBuf>,
+    /// The directory that data files named by the source are relative to, which is that of the file holding it
+    pub dir: PathBuf,
+}
+
+impl Source {
+    /// The source of a block in `chapter` with the body `body`, reading the file named by its `file=` option relative to
+    /// `dir`, the directory of the chapter
+    pub fn load(body: String, file: Option<&str>, dir: &Path, chapter: &Path) -> io::Result<Self> {
+        let Some(file) = file else {
+            return Ok(Self {
+                text: body,
+                file: None,
+                dir: dir.to_path_buf(),
+            });
+        };
+
+        let path = chapter.parent().unwrap_or(Path::new("")).join(file);
+        if !body.trim().is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "A block including `{}` with `file=` must be empty",
+                    path.display()
+                ),
+            ));
+        }
+
+        let full_path = dir.join(file);
+        let text = std::fs::read_to_string(&full_path)
+            .map_err(|e| io::Error::new(e.kind(), format!("{}: {e}", path.display())))?;
+
+        Ok(Self {
+            text,
+            file: Some(path),
+            dir: full_path.parent().unwrap_or(dir).to_path_buf(),
+        })
+    }
+
+    /// An error at the byte `offset` of the source, which is located in the included file if there is one
+    fn error(&self, offset: usize, msg: impl core::fmt::Display) -> io::Error {
+        let msg = match &self.file {
+            Some(file) => format!("{}:{}: {msg}", file.display(), line_of(&self.text, offset)),
+            None => msg.to_string(),
+        };
+        io::Error::new(io::ErrorKind::InvalidData, msg)
+    }
+
+    pub fn parse(&self) -> io::Result<Spanned<ast::Item<'_>>> {
+        parse_spanned(&self.text).map_err(|(offset, msg)| self.error(offset, msg))
+    }
+
+    /// Checks that an item parsed from the source is well-formed
+    pub fn check(&self, item: &ast::Item) -> io::Result<()> {
+        item.check().map_err(|e| match &self.file {
+            Some(_) => self.error(e.span.start, e.message),
+            None => io::Error::new(io::ErrorKind::InvalidData, e),
+        })
+    }
+}
+
+/// How a code block written in the specification language is treated, from the options that follow the language in
+/// its info string, such as `render` in `clever-spec,render`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BlockMode {
+    /// `clever-spec`: checked, and shown as source, such as to document the specification language itself
+    Source,
+    /// `clever-spec,render`: checked, and replaced by its rendering
+    Render,
+    /// `clever-spec,render,show-source`: rendered, followed by its source in a collapsible section
+    RenderWithSource,
+    /// `clever-spec,ignore`: shown as source without being checked, such as for examples of invalid syntax
+    Ignore,
+}
+
+impl BlockMode {
+    /// Whether the block's items are rendered, and so define the registers, instructions and tables they contain
+    pub const fn is_rendered(self) -> bool {
+        matches!(self, BlockMode::Render | BlockMode::RenderWithSource)
+    }
+}
+
+/// The options of the info string of a block written in the specification language
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BlockInfo<'a> {
+    pub mode: BlockMode,
+    /// The file holding the block's source, relative to the chapter, from a `file=` option such as
+    /// `file=tables/operand-props.clever-spec`
+    pub file: Option<&'a str>,
+}
+
+impl<'a> BlockInfo<'a> {
+    /// Parses the comma-separated options of an info string, which follow the language
+    pub fn from_options(options: impl IntoIterator<Item = &'a str>) -> Result<Self, String> {
+        let (mut render, mut show_source, mut ignore) = (false, false, false);
+        let mut file = None;
+        for option in options {
+            match option.trim() {
+                "render" => render = true,
+                "show-source" => show_source = true,
+                "ignore" => ignore = true,
+                "" => {}
+                option => match option.strip_prefix("file=") {
+                    Some(path) if !path.is_empty() => file = Some(path),
+                    _ => {
+                        return Err(format!(
+                            "Unknown option `{option}`, expected `render`, `show-source`, `ignore` or `file=<path>`"
+                        ));
+                    }
+                },
+            }
+        }
+
+        let mode = match (render, show_source, ignore) {
+            (false, false, false) => BlockMode::Source,
+            (true, false, false) => BlockMode::Render,
+            (true, true, false) => BlockMode::RenderWithSource,
+            (false, false, true) => BlockMode::Ignore,
+            (false, true, false) => {
+                return Err("`show-source` is only meaningful with `render`".to_string());
+            }
+            (_, _, true) => {
+                return Err("`ignore` cannot be combined with other options".to_string());
+            }
+        };
+
+        Ok(Self { mode, file })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(options: &str) -> Result<BlockInfo<'_>, String> {
+        BlockInfo::from_options(options.split(','))
+    }
+
+    #[test]
+    fn block_info_reads_modes_and_files() {
+        let mode = |options| info(options).unwrap().mode;
+        assert_eq!(mode(""), BlockMode::Source);
+        assert_eq!(mode("render"), BlockMode::Render);
+        assert_eq!(mode(" render , show-source"), BlockMode::RenderWithSource);
+        assert_eq!(mode("ignore"), BlockMode::Ignore);
+
+        let block = info("render,file=tables/ops.clever-spec").unwrap();
+        assert_eq!(block.mode, BlockMode::Render);
+        assert_eq!(block.file, Some("tables/ops.clever-spec"));
+    }
+
+    #[test]
+    fn block_info_rejects_unknown_and_conflicting_options() {
+        assert!(
+            info("rendr")
+                .unwrap_err()
+                .starts_with("Unknown option `rendr`")
+        );
+        assert!(info("file=").is_err());
+        assert!(info("show-source").is_err());
+        assert!(info("render,ignore").is_err());
+    }
+}
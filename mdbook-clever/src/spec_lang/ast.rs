@@ -6,7 +6,42 @@ use mdbook_fiction_tools::{
 };
 use xml::{EventWriter, writer::XmlEvent};
 
-use super::Spanned;
+use super::{SemanticError, Spanned};
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub enum Item<'src> {
+    Elem(Elem<'src>),
+    Encoding(Encoding<'src>),
+    Register(Register<'src>),
+    Exception(Exception<'src>),
+}
+
+impl<'src> Item<'src> {
+    pub fn check(&self) -> Result<(), SemanticError> {
+        match self {
+            Item::Elem(Elem::Table(table)) => table.check(),
+            Item::Elem(_) => Ok(()),
+            Item::Encoding(enc) => enc.check(),
+            Item::Register(reg) => reg.check(),
+            Item::Exception(_) => Ok(()),
+        }
+    }
+
+    pub fn write_xhtml<W: std::io::Write>(
+        &self,
+        writer: &mut EventWriter<W>,
+    ) -> xml::writer::Result<()> {
+        match self {
+            Item::Elem(Elem::Table(table)) if table.caption.is_some() => {
+                table.write_captioned_xhtml(writer)
+            }
+            Item::Elem(elem) => write_rich_node(&elem.to_rich_text(), writer),
+            Item::Encoding(enc) => enc.write_xhtml(writer),
+            Item::Register(reg) => reg.write_xhtml(writer),
+            Item::Exception(exception) => exception.write_xhtml(writer),
+        }
+    }
+}
 
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
 pub enum Elem<'src> {
@@ -16,6 +51,35 @@ pub enum Elem<'src> {
 }
 
 impl<'src> Elem<'src> {
+    /// Replaces every occurrence of `{var}` in the element with `value`
+    pub fn interpolate(&self, var: &str, value: &str) -> Elem<'src> {
+        let pattern = format!("{{{var}}}");
+        let replace = |st: &Cow<'src, str>| {
+            if st.contains(&pattern) {
+                Cow::Owned(st.replace(&pattern, value))
+            } else {
+                st.clone()
+            }
+        };
+
+        match self {
+            Elem::Table(table) => Elem::Table(Table {
+                label: table.label.clone(),
+                caption: table.caption.clone(),
+                number: table.number.clone(),
+                source: table.source.clone(),
+                heading: table.heading.as_ref().map(|h| h.interpolate(var, value)),
+                rows: table
+                    .rows
+                    .iter()
+                    .map(|r| r.interpolate(var, value))
+                    .collect(),
+            }),
+            Elem::MarkdownLiteral(st) => Elem::MarkdownLiteral(replace(st)),
+            Elem::StringLiteral(st) => Elem::StringLiteral(replace(st)),
+        }
+    }
+
     pub fn to_rich_text<'a>(&'a self) -> RichText<'a> {
         match self {
             Elem::Table(table) => {
@@ -75,10 +139,147 @@ impl<'src> Elem<'src> {
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
 pub struct Table<'src> {
     pub label: Spanned<IPath<'src>>,
+    /// The caption shown with the table, from `caption "..."` or `caption <!...!>`
+    pub caption: Option<Box<Spanned<Elem<'src>>>>,
+    /// The number of a captioned table, such as `3.2`, which is assigned when the book is built
+    pub number: Option<String>,
+    /// The data file the rows of the table are loaded from, if any
+    pub source: Option<Box<Spanned<DataSource<'src>>>>,
     pub heading: Option<Spanned<Array<'src>>>,
     pub rows: Vec<Spanned<Array<'src>>>,
 }
 
+impl<'src> Table<'src> {
+    /// Checks that every row of the table and of any table nested in a cell is as wide as its heading, or as its first
+    /// row if it has no heading, and that no nested table has a caption, since only the outermost table of a block is
+    /// captioned
+    pub fn check(&self) -> Result<(), SemanticError> {
+        fn check_nested(table: &Table) -> Result<(), SemanticError> {
+            let mut rows = table.heading.iter().chain(&table.rows);
+            if let Some(first) = rows.next() {
+                let width = first.body.0.len();
+                if let Some(row) = rows.find(|row| row.body.0.len() != width) {
+                    return Err(SemanticError::new(
+                        row.span.clone(),
+                        format!(
+                            "Row is {} cells wide, but the table has {width} columns",
+                            row.body.0.len()
+                        ),
+                    ));
+                }
+            }
+
+            for cell in table
+                .heading
+                .iter()
+                .chain(&table.rows)
+                .flat_map(|row| &row.body.0)
+            {
+                if let Elem::Table(nested) = &cell.body {
+                    if let Some(caption) = &nested.caption {
+                        return Err(SemanticError::new(
+                            caption.span.clone(),
+                            "Only the outermost table of a block may have a caption",
+                        ));
+                    }
+                    check_nested(nested)?;
+                }
+            }
+            Ok(())
+        }
+
+        check_nested(self)
+    }
+
+    /// The anchor of a captioned table, such as `table-t-1` for `T:1`
+    pub fn anchor(&self) -> String {
+        let label = self
+            .label
+            .body
+            .0
+            .iter()
+            .map(|id| id.body.0.to_lowercase())
+            .collect::<Vec<_>>()
+            .join("-");
+        format!("table-{label}")
+    }
+
+    /// The source text of the caption, if the table has one
+    pub fn caption_text(&self) -> Option<&str> {
+        match &self.caption.as_deref()?.body {
+            Elem::MarkdownLiteral(text) | Elem::StringLiteral(text) => Some(text.trim()),
+            Elem::Table(_) => None,
+        }
+    }
+
+    /// The number shown before the caption, such as `Table 3.2: `
+    pub fn number_prefix(&self) -> String {
+        self.number
+            .as_ref()
+            .map_or_else(String::new, |number| format!("Table {number}: "))
+    }
+
+    /// Writes a captioned table, with its `<caption>` directly after the opening tag. Rich text tables have no caption,
+    /// so only the cells are written as rich text.
+    fn write_captioned_xhtml<W: std::io::Write>(
+        &self,
+        w: &mut EventWriter<W>,
+    ) -> xml::writer::Result<()> {
+        w.write(XmlEvent::start_element("table"))?;
+        if let Some(caption) = &self.caption {
+            w.write(XmlEvent::start_element("caption").attr("id", &self.anchor()))?;
+            w.write(XmlEvent::characters(&self.number_prefix()))?;
+            write_rich_node(&caption.body.to_rich_text(), w)?;
+            w.write(XmlEvent::end_element())?;
+        }
+
+        let write_row = |w: &mut EventWriter<W>, row: &Array, cell: &str| {
+            w.write(XmlEvent::start_element("tr"))?;
+            for elem in &row.0 {
+                w.write(XmlEvent::start_element(cell))?;
+                write_rich_node(&elem.body.to_rich_text(), w)?;
+                w.write(XmlEvent::end_element())?;
+            }
+            w.write(XmlEvent::end_element())
+        };
+
+        if let Some(heading) = &self.heading {
+            w.write(XmlEvent::start_element("thead"))?;
+            write_row(w, &heading.body, "th")?;
+            w.write(XmlEvent::end_element())?;
+        }
+        w.write(XmlEvent::start_element("tbody"))?;
+        for row in &self.rows {
+            write_row(w, &row.body, "td")?;
+        }
+        w.write(XmlEvent::end_element())?;
+        w.write(XmlEvent::end_element())
+    }
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct DataSource<'src> {
+    pub path: Spanned<Cow<'src, str>>,
+    /// For TOML files, the key of the array of tables that contains the rows
+    pub key: Option<Spanned<Id<'src>>>,
+    /// The names of the columns to keep, which are written as identifiers or, for names such as `h-field`, as strings
+    pub columns: Option<Spanned<Vec<Spanned<Cow<'src, str>>>>>,
+    pub filters: Vec<Spanned<Filter<'src>>>,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Filter<'src> {
+    pub column: Spanned<Cow<'src, str>>,
+    pub negated: bool,
+    pub value: Spanned<Cow<'src, str>>,
+}
+
+impl<'src> Filter<'src> {
+    pub fn matches(&self, value: &str) -> bool {
+        (value == self.value.body) != self.negated
+    }
+}
+
 #[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
 pub struct Id<'src>(pub &'src str);
 
@@ -87,3 +288,583 @@ pub struct IPath<'src>(pub Vec<Spanned<Id<'src>>>);
 
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
 pub struct Array<'src>(pub Vec<Spanned<Elem<'src>>>);
+
+impl<'src> Spanned<Array<'src>> {
+    pub fn interpolate(&self, var: &str, value: &str) -> Self {
+        Spanned {
+            body: Array(
+                self.body
+                    .0
+                    .iter()
+                    .map(|e| Spanned {
+                        body: e.body.interpolate(var, value),
+                        span: e.span.clone(),
+                    })
+                    .collect(),
+            ),
+            span: self.span.clone(),
+        }
+    }
+}
+
+/// An inclusive range of bits, `[hi:lo]`
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub struct BitRange {
+    pub hi: u32,
+    pub lo: u32,
+}
+
+impl BitRange {
+    /// The number of bits in the range, which is 1 for a reversed range
+    pub const fn width(&self) -> u32 {
+        self.hi.saturating_sub(self.lo) + 1
+    }
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub enum EncodingField<'src> {
+    Field(Spanned<Id<'src>>, Spanned<BitRange>),
+    Fixed(Spanned<Cow<'src, str>>, Spanned<BitRange>),
+}
+
+impl<'src> EncodingField<'src> {
+    pub fn range(&self) -> &Spanned<BitRange> {
+        match self {
+            EncodingField::Field(_, range) | EncodingField::Fixed(_, range) => range,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Encoding<'src> {
+    pub label: Spanned<IPath<'src>>,
+    pub width: Spanned<u32>,
+    pub fields: Vec<Spanned<EncodingField<'src>>>,
+}
+
+/// Checks that every field lies within `width` bits, that fixed bits match the width of their field,
+/// and that no two fields overlap.
+///
+/// Returns the field that owns each bit, if any, indexed by bit number
+fn check_fields<'a, 'src>(
+    width: u32,
+    fields: &'a [Spanned<EncodingField<'src>>],
+) -> Result<Vec<Option<&'a Spanned<EncodingField<'src>>>>, SemanticError> {
+    let mut owners: Vec<Option<&Spanned<EncodingField>>> = vec![None; width as usize];
+
+    for field in fields {
+        let range = field.body.range();
+        let BitRange { hi, lo } = range.body;
+        if hi < lo {
+            return Err(SemanticError::new(
+                range.span.clone(),
+                format!("Bit range [{hi}:{lo}] is reversed"),
+            ));
+        }
+        if hi >= width {
+            return Err(SemanticError::new(
+                range.span.clone(),
+                format!("Bit {hi} is outside of the {width}-bit layout"),
+            ));
+        }
+
+        if let EncodingField::Fixed(bits, _) = &field.body {
+            if bits.body.len() != range.body.width() as usize {
+                return Err(SemanticError::new(
+                    bits.span.clone(),
+                    format!(
+                        "Fixed bits `{}` do not match the width of [{hi}:{lo}]",
+                        bits.body
+                    ),
+                ));
+            }
+            if let Some(c) = bits.body.chars().find(|c| !matches!(c, '0' | '1' | 'x')) {
+                return Err(SemanticError::new(
+                    bits.span.clone(),
+                    format!("Invalid fixed bit `{c}`, expected one of `0`, `1`, or `x`"),
+                ));
+            }
+        }
+
+        for bit in lo..=hi {
+            if let Some(prev) = owners[bit as usize].replace(field) {
+                return Err(SemanticError::new(
+                    field.span.clone(),
+                    format!(
+                        "Bit {bit} overlaps with the field at {}:{}",
+                        prev.span.start, prev.span.end
+                    ),
+                ));
+            }
+        }
+    }
+
+    Ok(owners)
+}
+
+impl<'src> Encoding<'src> {
+    /// The widest encoding that may be declared, in bits
+    pub const MAX_WIDTH: u32 = 256;
+
+    /// Checks that the fields are valid and cover every bit of the encoding exactly once
+    pub fn check(&self) -> Result<(), SemanticError> {
+        if self.width.body > Self::MAX_WIDTH {
+            return Err(SemanticError::new(
+                self.width.span.clone(),
+                format!(
+                    "An encoding is at most {} bits wide, not {}",
+                    Self::MAX_WIDTH,
+                    self.width.body
+                ),
+            ));
+        }
+
+        let owners = check_fields(self.width.body, &self.fields)?;
+
+        match owners.iter().rposition(Option::is_none) {
+            Some(bit) => Err(SemanticError::new(
+                self.label.span.clone(),
+                format!("Bit {bit} of the encoding is not covered by any field"),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    pub fn write_xhtml<W: std::io::Write>(
+        &self,
+        w: &mut EventWriter<W>,
+    ) -> xml::writer::Result<()> {
+        let mut fields = self.fields.iter().map(|f| &f.body).collect::<Vec<_>>();
+        fields.sort_by_key(|f| core::cmp::Reverse(f.range().body.hi));
+
+        w.write(XmlEvent::start_element("table").attr("class", "clever-encoding"))?;
+        w.write(XmlEvent::start_element("thead"))?;
+        w.write(XmlEvent::start_element("tr"))?;
+        for bit in (0..self.width.body).rev() {
+            w.write(XmlEvent::start_element("th"))?;
+            w.write(XmlEvent::characters(&bit.to_string()))?;
+            w.write(XmlEvent::end_element())?;
+        }
+        w.write(XmlEvent::end_element())?;
+        w.write(XmlEvent::end_element())?;
+
+        w.write(XmlEvent::start_element("tbody"))?;
+        w.write(XmlEvent::start_element("tr"))?;
+        for field in fields {
+            match field {
+                EncodingField::Field(name, range) => {
+                    let span = range.body.width().to_string();
+                    w.write(
+                        XmlEvent::start_element("td")
+                            .attr("colspan", &span)
+                            .attr("style", "text-align: center"),
+                    )?;
+                    w.write(XmlEvent::start_element("code"))?;
+                    w.write(XmlEvent::characters(name.body.0))?;
+                    w.write(XmlEvent::end_element())?;
+                    w.write(XmlEvent::end_element())?;
+                }
+                EncodingField::Fixed(bits, _) => {
+                    for bit in bits.body.chars() {
+                        w.write(XmlEvent::start_element("td").attr("style", "text-align: center"))?;
+                        w.write(XmlEvent::characters(bit.encode_utf8(&mut [0; 4])))?;
+                        w.write(XmlEvent::end_element())?;
+                    }
+                }
+            }
+        }
+        w.write(XmlEvent::end_element())?;
+        w.write(XmlEvent::end_element())?;
+        w.write(XmlEvent::end_element())
+    }
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Register<'src> {
+    pub name: Spanned<Id<'src>>,
+    pub number: Spanned<u32>,
+    pub aliases: Vec<Spanned<Id<'src>>>,
+    pub properties: Vec<Spanned<Id<'src>>>,
+    pub fields: Vec<Spanned<EncodingField<'src>>>,
+    /// Fields declared without a bit range, because the specification does not yet give their position
+    pub unspecified_fields: Vec<Spanned<Id<'src>>>,
+}
+
+impl<'src> Register<'src> {
+    pub const WIDTH: u32 = 64;
+
+    pub fn anchor(name: &str) -> String {
+        format!("register-{name}")
+    }
+
+    pub fn check(&self) -> Result<(), SemanticError> {
+        check_fields(Self::WIDTH, &self.fields)?;
+
+        let mut names = self
+            .fields
+            .iter()
+            .filter_map(|f| match &f.body {
+                EncodingField::Field(name, _) => Some(name),
+                EncodingField::Fixed(..) => None,
+            })
+            .collect::<Vec<_>>();
+        for name in &self.unspecified_fields {
+            if names.iter().any(|n| n.body == name.body) {
+                return Err(SemanticError::new(
+                    name.span.clone(),
+                    format!("Field `{}` is declared more than once", name.body.0),
+                ));
+            }
+            names.push(name);
+        }
+
+        Ok(())
+    }
+
+    /// The columns of the register's field diagram, from the most significant bit down.
+    ///
+    /// A 64-bit register is too wide to draw bit-by-bit, so each field (and each reserved gap between fields, which has
+    /// no field) gets a single column headed by its bit range
+    pub fn field_columns(&self) -> Vec<(BitRange, Option<&EncodingField<'src>>)> {
+        let mut fields = self.fields.iter().map(|f| &f.body).collect::<Vec<_>>();
+        fields.sort_by_key(|f| core::cmp::Reverse(f.range().body.hi));
+
+        let mut columns = Vec::new();
+        let mut next = Self::WIDTH;
+        for field in fields {
+            let range = field.range().body;
+            if range.hi + 1 < next {
+                columns.push((
+                    BitRange {
+                        hi: next - 1,
+                        lo: range.hi + 1,
+                    },
+                    None,
+                ));
+            }
+            columns.push((range, Some(field)));
+            next = range.lo;
+        }
+        if next > 0 {
+            columns.push((
+                BitRange {
+                    hi: next - 1,
+                    lo: 0,
+                },
+                None,
+            ));
+        }
+
+        columns
+    }
+
+    pub fn write_xhtml<W: std::io::Write>(
+        &self,
+        w: &mut EventWriter<W>,
+    ) -> xml::writer::Result<()> {
+        let anchor = Self::anchor(self.name.body.0);
+        w.write(
+            XmlEvent::start_element("table")
+                .attr("class", "clever-register")
+                .attr("id", &anchor),
+        )?;
+        w.write(XmlEvent::start_element("thead"))?;
+        w.write(XmlEvent::start_element("tr"))?;
+        for heading in ["Number", "Name", "Alias Names", "Properties"] {
+            w.write(XmlEvent::start_element("th"))?;
+            w.write(XmlEvent::characters(heading))?;
+            w.write(XmlEvent::end_element())?;
+        }
+        w.write(XmlEvent::end_element())?;
+        w.write(XmlEvent::end_element())?;
+
+        w.write(XmlEvent::start_element("tbody"))?;
+        w.write(XmlEvent::start_element("tr"))?;
+        w.write(XmlEvent::start_element("td"))?;
+        w.write(XmlEvent::start_element("code"))?;
+        w.write(XmlEvent::characters(&self.number.body.to_string()))?;
+        w.write(XmlEvent::end_element())?;
+        w.write(XmlEvent::end_element())?;
+        w.write(XmlEvent::start_element("td"))?;
+        w.write(XmlEvent::characters(self.name.body.0))?;
+        w.write(XmlEvent::end_element())?;
+        w.write(XmlEvent::start_element("td"))?;
+        for (i, alias) in self.aliases.iter().enumerate() {
+            if i != 0 {
+                w.write(XmlEvent::characters(", "))?;
+            }
+            w.write(XmlEvent::start_element("code"))?;
+            w.write(XmlEvent::characters(alias.body.0))?;
+            w.write(XmlEvent::end_element())?;
+        }
+        w.write(XmlEvent::end_element())?;
+        w.write(XmlEvent::start_element("td"))?;
+        // An empty list is not valid XHTML
+        if !self.properties.is_empty() {
+            w.write(XmlEvent::start_element("ul"))?;
+            for prop in &self.properties {
+                w.write(XmlEvent::start_element("li"))?;
+                w.write(XmlEvent::characters(prop.body.0))?;
+                w.write(XmlEvent::end_element())?;
+            }
+            w.write(XmlEvent::end_element())?;
+        }
+        w.write(XmlEvent::end_element())?;
+        w.write(XmlEvent::end_element())?;
+        w.write(XmlEvent::end_element())?;
+        w.write(XmlEvent::end_element())?;
+
+        // Field references link to the diagram, or to the list of unspecified fields if there is no diagram
+        let fields_anchor = format!("{anchor}-fields");
+        if !self.fields.is_empty() {
+            self.write_field_diagram_xhtml(&fields_anchor, w)?;
+        }
+        if !self.unspecified_fields.is_empty() {
+            let id = self.fields.is_empty().then_some(&*fields_anchor);
+            self.write_unspecified_fields_xhtml(id, w)?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the diagram of the fields with a bit range, from the most significant bit down
+    fn write_field_diagram_xhtml<W: std::io::Write>(
+        &self,
+        id: &str,
+        w: &mut EventWriter<W>,
+    ) -> xml::writer::Result<()> {
+        let columns = self.field_columns();
+
+        w.write(
+            XmlEvent::start_element("table")
+                .attr("class", "clever-register-fields")
+                .attr("id", id),
+        )?;
+        w.write(XmlEvent::start_element("thead"))?;
+        w.write(XmlEvent::start_element("tr"))?;
+        for (range, _) in &columns {
+            let bits = if range.hi == range.lo {
+                range.hi.to_string()
+            } else {
+                format!("{}:{}", range.hi, range.lo)
+            };
+            w.write(XmlEvent::start_element("th"))?;
+            w.write(XmlEvent::characters(&bits))?;
+            w.write(XmlEvent::end_element())?;
+        }
+        w.write(XmlEvent::end_element())?;
+        w.write(XmlEvent::end_element())?;
+
+        w.write(XmlEvent::start_element("tbody"))?;
+        w.write(XmlEvent::start_element("tr"))?;
+        for (_, field) in &columns {
+            w.write(XmlEvent::start_element("td").attr("style", "text-align: center"))?;
+            match field {
+                Some(EncodingField::Field(name, _)) => {
+                    w.write(XmlEvent::start_element("code"))?;
+                    w.write(XmlEvent::characters(&format!(
+                        "{}.{}",
+                        self.name.body.0, name.body.0
+                    )))?;
+                    w.write(XmlEvent::end_element())?;
+                }
+                Some(EncodingField::Fixed(bits, _)) => {
+                    w.write(XmlEvent::characters(&bits.body))?;
+                }
+                None => {
+                    w.write(XmlEvent::start_element("em"))?;
+                    w.write(XmlEvent::characters("reserved"))?;
+                    w.write(XmlEvent::end_element())?;
+                }
+            }
+            w.write(XmlEvent::end_element())?;
+        }
+        w.write(XmlEvent::end_element())?;
+        w.write(XmlEvent::end_element())?;
+        w.write(XmlEvent::end_element())
+    }
+
+    /// Writes the fields whose bits are not yet specified, which are not part of the field diagram
+    fn write_unspecified_fields_xhtml<W: std::io::Write>(
+        &self,
+        id: Option<&str>,
+        w: &mut EventWriter<W>,
+    ) -> xml::writer::Result<()> {
+        let table = XmlEvent::start_element("table").attr("class", "clever-register-unspecified");
+        w.write(match id {
+            Some(id) => table.attr("id", id),
+            None => table,
+        })?;
+        w.write(XmlEvent::start_element("thead"))?;
+        w.write(XmlEvent::start_element("tr"))?;
+        for heading in ["Field", "Bits"] {
+            w.write(XmlEvent::start_element("th"))?;
+            w.write(XmlEvent::characters(heading))?;
+            w.write(XmlEvent::end_element())?;
+        }
+        w.write(XmlEvent::end_element())?;
+        w.write(XmlEvent::end_element())?;
+
+        w.write(XmlEvent::start_element("tbody"))?;
+        for name in &self.unspecified_fields {
+            w.write(XmlEvent::start_element("tr"))?;
+            w.write(XmlEvent::start_element("td"))?;
+            w.write(XmlEvent::start_element("code"))?;
+            w.write(XmlEvent::characters(&format!(
+                "{}.{}",
+                self.name.body.0, name.body.0
+            )))?;
+            w.write(XmlEvent::end_element())?;
+            w.write(XmlEvent::end_element())?;
+            w.write(XmlEvent::start_element("td"))?;
+            w.write(XmlEvent::start_element("em"))?;
+            w.write(XmlEvent::characters("not yet specified"))?;
+            w.write(XmlEvent::end_element())?;
+            w.write(XmlEvent::end_element())?;
+            w.write(XmlEvent::end_element())?;
+        }
+        w.write(XmlEvent::end_element())?;
+        w.write(XmlEvent::end_element())
+    }
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct Exception<'src> {
+    pub name: Spanned<Id<'src>>,
+    /// The vector number, unless it is not yet assigned
+    pub vector: Option<Spanned<u32>>,
+    /// What the error code pushed with the exception holds
+    pub code: Option<Box<Spanned<Elem<'src>>>>,
+    pub description: Option<Box<Spanned<Elem<'src>>>>,
+}
+
+impl<'src> Exception<'src> {
+    pub fn anchor(name: &str) -> String {
+        format!("exception-{}", name.to_ascii_lowercase())
+    }
+
+    /// Writes a vector number, or that it is not yet assigned
+    pub fn write_vector_xhtml<W: std::io::Write>(
+        vector: Option<u32>,
+        w: &mut EventWriter<W>,
+    ) -> xml::writer::Result<()> {
+        match vector {
+            Some(vector) => {
+                w.write(XmlEvent::start_element("code"))?;
+                w.write(XmlEvent::characters(&vector.to_string()))?;
+            }
+            None => return Self::write_unassigned_xhtml(w),
+        }
+        w.write(XmlEvent::end_element())
+    }
+
+    /// Writes an error code, or that it is not yet assigned
+    pub fn write_code_xhtml<W: std::io::Write>(
+        code: Option<&RichText>,
+        w: &mut EventWriter<W>,
+    ) -> xml::writer::Result<()> {
+        match code {
+            Some(code) => write_rich_node(code, w),
+            None => Self::write_unassigned_xhtml(w),
+        }
+    }
+
+    fn write_unassigned_xhtml<W: std::io::Write>(
+        w: &mut EventWriter<W>,
+    ) -> xml::writer::Result<()> {
+        w.write(XmlEvent::start_element("em"))?;
+        w.write(XmlEvent::characters("unassigned"))?;
+        w.write(XmlEvent::end_element())
+    }
+
+    pub fn write_xhtml<W: std::io::Write>(
+        &self,
+        w: &mut EventWriter<W>,
+    ) -> xml::writer::Result<()> {
+        w.write(
+            XmlEvent::start_element("table")
+                .attr("class", "clever-exception")
+                .attr("id", &Self::anchor(self.name.body.0)),
+        )?;
+        w.write(XmlEvent::start_element("thead"))?;
+        w.write(XmlEvent::start_element("tr"))?;
+        for heading in ["Mnemonic", "Vector", "Error Code", "Description"] {
+            w.write(XmlEvent::start_element("th"))?;
+            w.write(XmlEvent::characters(heading))?;
+            w.write(XmlEvent::end_element())?;
+        }
+        w.write(XmlEvent::end_element())?;
+        w.write(XmlEvent::end_element())?;
+
+        w.write(XmlEvent::start_element("tbody"))?;
+        w.write(XmlEvent::start_element("tr"))?;
+        w.write(XmlEvent::start_element("td"))?;
+        w.write(XmlEvent::start_element("strong"))?;
+        w.write(XmlEvent::characters(self.name.body.0))?;
+        w.write(XmlEvent::end_element())?;
+        w.write(XmlEvent::end_element())?;
+        w.write(XmlEvent::start_element("td"))?;
+        Self::write_vector_xhtml(self.vector.as_ref().map(|v| v.body), w)?;
+        w.write(XmlEvent::end_element())?;
+        w.write(XmlEvent::start_element("td"))?;
+        let code = self.code.as_ref().map(|elem| elem.body.to_rich_text());
+        Self::write_code_xhtml(code.as_ref(), w)?;
+        w.write(XmlEvent::end_element())?;
+        w.write(XmlEvent::start_element("td"))?;
+        if let Some(elem) = &self.description {
+            write_rich_node(&elem.body.to_rich_text(), w)?;
+        }
+        w.write(XmlEvent::end_element())?;
+        w.write(XmlEvent::end_element())?;
+        w.write(XmlEvent::end_element())?;
+        w.write(XmlEvent::end_element())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encoding(src: &str) -> Encoding<'_> {
+        match crate::spec_lang::parse_spanned(src).unwrap().body {
+            Item::Encoding(enc) => enc,
+            item => panic!("expected an encoding, found {item:?}"),
+        }
+    }
+
+    #[test]
+    fn check_fields_finds_owners() {
+        let enc = encoding(r#"encoding E:x 4 { field a [3:2], fixed "0x" [1:0] }"#);
+        let owners = check_fields(4, &enc.fields).unwrap();
+        assert_eq!(owners[3], Some(&enc.fields[0]));
+        assert_eq!(owners[0], Some(&enc.fields[1]));
+        assert!(enc.check().is_ok());
+    }
+
+    #[test]
+    fn check_fields_rejects_reversed_ranges() {
+        let enc = encoding("encoding E:x 8 { field a [0:3] }");
+        let err = check_fields(8, &enc.fields).unwrap_err();
+        assert!(err.message.contains("reversed"), "{}", err.message);
+    }
+
+    #[test]
+    fn check_fields_rejects_overlapping_and_outside_ranges() {
+        let enc = encoding("encoding E:x 8 { field a [7:4], field b [4:0] }");
+        let err = check_fields(8, &enc.fields).unwrap_err();
+        assert!(err.message.starts_with("Bit 4 overlaps"), "{}", err.message);
+
+        let enc = encoding("encoding E:x 8 { field a [8:0] }");
+        assert!(check_fields(8, &enc.fields).is_err());
+    }
+
+    #[test]
+    fn check_rejects_oversized_and_uncovered_encodings() {
+        let enc = encoding("encoding E:x 4294967295 { field a [0] }");
+        assert!(enc.check().is_err());
+
+        let enc = encoding("encoding E:x 8 { field a [7:1] }");
+        let err = enc.check().unwrap_err();
+        assert!(err.message.starts_with("Bit 0"), "{}", err.message);
+    }
+}
@@ -7,7 +7,10 @@ use parse_error::{ErrorContext, Expectation};
 
 use super::{
     Spanned, Token,
-    ast::{Array, Elem, IPath, Id, Table},
+    ast::{
+        Array, BitRange, DataSource, Elem, Encoding, EncodingField, Exception, Filter, IPath, Id,
+        Item, Register, Table,
+    },
 };
 
 pub mod parse_error;
@@ -17,7 +20,7 @@ pub use parse_error::Error;
 use nom::{
     Parser,
     branch::alt,
-    combinator::{all_consuming, complete, cut, map_opt, opt},
+    combinator::{self, all_consuming, complete, cut, map_opt, opt},
     error::context,
     multi, sequence,
 };
@@ -26,6 +29,9 @@ pub type Input<'src, 'a> = &'a [Spanned<Token<'src>>];
 
 pub type IResult<'src, 'a, O> = nom::IResult<Input<'src, 'a>, Spanned<O>, Error<Input<'src, 'a>>>;
 
+/// The most rows a `for` loop may generate, which is the size of the opcode space
+const MAX_LOOP_ROWS: u64 = 4096;
+
 pub fn tag<'src, T: PartialEq<Token<'src>> + Into<ErrorContext> + Clone>(
     tag: T,
 ) -> impl for<'a> FnMut(Input<'src, 'a>) -> IResult<'src, 'a, Token<'a>> {
@@ -41,6 +47,32 @@ pub fn tag<'src, T: PartialEq<Token<'src>> + Into<ErrorContext> + Clone>(
     }
 }
 
+/// Parses a keyword that is only reserved where it is expected, such as `field` in an encoding, so that it can still be
+/// used as a name elsewhere. It is lexed as an identifier, and gives [`Token::Identifier`].
+pub fn keyword<'src>(
+    name: &'static str,
+) -> impl for<'a> FnMut(Input<'src, 'a>) -> IResult<'src, 'a, Token<'a>> + Clone {
+    move |input| match input {
+        [
+            Spanned {
+                body: Token::Identifier(id),
+                span,
+            },
+            rest @ ..,
+        ] if *id == name => Ok((
+            rest,
+            Spanned {
+                body: Token::Identifier(id),
+                span: span.clone(),
+            },
+        )),
+        rest => Err(nom::Err::Error(Error::create(
+            rest,
+            Expectation::Keyword(name),
+        ))),
+    }
+}
+
 pub fn commit_after<I, O, E, F>(
     mut n: usize,
     mut f: F,
@@ -81,6 +113,109 @@ pub fn parse_id<'src, 'a>(input: Input<'src, 'a>) -> IResult<'src, 'a, Id<'src>>
     }
 }
 
+pub fn parse_int_literal(id: &str) -> Option<u64> {
+    if let Some(hex) = id.strip_prefix("0x") {
+        u64::from_str_radix(hex, 16).ok()
+    } else if let Some(oct) = id.strip_prefix("0o") {
+        u64::from_str_radix(oct, 8).ok()
+    } else if let Some(bin) = id.strip_prefix("0b") {
+        u64::from_str_radix(bin, 2).ok()
+    } else {
+        id.parse().ok()
+    }
+}
+
+pub fn parse_int<'src, 'a>(input: Input<'src, 'a>) -> IResult<'src, 'a, u64> {
+    match input {
+        [
+            Spanned {
+                body: Token::Identifier(id),
+                span,
+            },
+            rest @ ..,
+        ] => match parse_int_literal(id) {
+            Some(val) => Ok((
+                rest,
+                Spanned {
+                    body: val,
+                    span: span.clone(),
+                },
+            )),
+            None => Err(nom::Err::Error(Error::create(input, Expectation::Integer))),
+        },
+        rest => Err(nom::Err::Error(Error::create(rest, Expectation::Integer))),
+    }
+}
+
+pub fn parse_bit<'src, 'a>(input: Input<'src, 'a>) -> IResult<'src, 'a, u32> {
+    let (rest, val) = parse_int(input)?;
+
+    match u32::try_from(val.body) {
+        Ok(bit) => Ok((
+            rest,
+            Spanned {
+                body: bit,
+                span: val.span,
+            },
+        )),
+        Err(_) => Err(nom::Err::Failure(Error::create(
+            input,
+            Expectation::Integer,
+        ))),
+    }
+}
+
+pub fn parse_bit_range<'src, 'a>(input: Input<'src, 'a>) -> IResult<'src, 'a, BitRange> {
+    match input {
+        [
+            Spanned {
+                body: Token::Bracket(body),
+                span,
+            },
+            rest @ ..,
+        ] => {
+            let range = match cut(all_consuming(multi::separated_list1(
+                tag(Token::LabelSep),
+                parse_bit,
+            )))(body)
+            {
+                Ok((_, bits)) => bits,
+                Err(e) => {
+                    return Err(e.map(|e| {
+                        e.with_context_and_input(input, ErrorContext::Context("In bit range"))
+                    }));
+                }
+            };
+
+            let range = match range.as_slice() {
+                [hi, lo] => BitRange {
+                    hi: hi.body,
+                    lo: lo.body,
+                },
+                [bit] => BitRange {
+                    hi: bit.body,
+                    lo: bit.body,
+                },
+                _ => {
+                    return Err(nom::Err::Failure(Error::create(
+                        input,
+                        Expectation::BitRange,
+                    )));
+                }
+            };
+
+            Ok((
+                rest,
+                Spanned {
+                    body: range,
+                    span: span.clone(),
+                },
+            ))
+        }
+        i => Err(nom::Err::Error(Error::create(i, Expectation::BitRange))),
+    }
+}
+
 pub fn parse_path<'src, 'a>(input: Input<'src, 'a>) -> IResult<'src, 'a, IPath<'src>> {
     multi::separated_list1(tag(Token::LabelSep), commit_after(1, parse_id))(input).map(|(i, v)| {
         let begin_span = &v.first().expect("list must have an element").span;
@@ -356,25 +491,69 @@ where
     }
 }
 
-pub fn parse_table<'src, 'a>(input: Input<'src, 'a>) -> IResult<'src, 'a, Table<'src>> {
-    let (
+/// Parses the caption of a table, `caption "..."` or `caption <!...!>`
+fn parse_caption<'src, 'a>(input: Input<'src, 'a>) -> IResult<'src, 'a, Elem<'src>> {
+    let (rest, (kw, caption)) = sequence::tuple((
+        keyword("caption"),
+        cut(alt((
+            map_spanned(parse_markdown, Elem::MarkdownLiteral),
+            map_spanned(parse_str, Elem::StringLiteral),
+        ))),
+    ))(input)?;
+
+    let kw: Spanned<Token> = kw;
+
+    let span = Span {
+        start: kw.span.start,
+        end: caption.span.end,
+    };
+
+    Ok((
         rest,
-        (
-            a,
-            label,
-            head,
-            Spanned {
-                body: rows,
-                span: end_span,
-            },
-        ),
-    ) = sequence::tuple((
+        Spanned {
+            body: caption.body,
+            span,
+        },
+    ))
+}
+
+pub fn parse_table<'src, 'a>(input: Input<'src, 'a>) -> IResult<'src, 'a, Table<'src>> {
+    let (rest, (a, label, caption, head)) = sequence::tuple((
         tag(Token::KwTable),
         cut(parse_path),
+        opt(parse_caption),
         opt(map_spanned(bracket(parse_elem), Array)),
-        cut(brace(parse_row)),
     ))(input)?;
 
+    let columns = head
+        .iter()
+        .flat_map(|head| &head.body.0)
+        .map(|elem| match &elem.body {
+            Elem::StringLiteral(st) | Elem::MarkdownLiteral(st) => st.to_string(),
+            Elem::Table(_) => String::new(),
+        })
+        .collect::<Vec<_>>();
+
+    let (rest, source) = opt(parse_data_source)(rest)?;
+
+    let (rest, rows, end_span) = match &source {
+        Some(source) => (rest, Vec::new(), source.span.clone()),
+        None => {
+            let (
+                rest,
+                Spanned {
+                    body: entries,
+                    span: end_span,
+                },
+            ) = cut(brace(parse_table_entry(columns)))(rest)?;
+            (
+                rest,
+                entries.into_iter().flat_map(|e| e.body).collect(),
+                end_span,
+            )
+        }
+    };
+
     let a: Spanned<Token> = a;
 
     let span = Span {
@@ -387,6 +566,9 @@ pub fn parse_table<'src, 'a>(input: Input<'src, 'a>) -> IResult<'src, 'a, Table<
         Spanned {
             body: Table {
                 label,
+                caption: caption.map(Box::new),
+                number: None,
+                source: source.map(Box::new),
                 heading: head,
                 rows,
             },
@@ -395,6 +577,283 @@ pub fn parse_table<'src, 'a>(input: Input<'src, 'a>) -> IResult<'src, 'a, Table<
     ))
 }
 
+enum DataClause<'src> {
+    Key(Spanned<Id<'src>>),
+    Columns(Spanned<Vec<Spanned<Cow<'src, str>>>>),
+    Where(Filter<'src>),
+}
+
+/// Parses the name of a data file column, as an identifier or as a string for names that are not identifiers
+fn parse_column<'src, 'a>(input: Input<'src, 'a>) -> IResult<'src, 'a, Cow<'src, str>> {
+    alt((
+        combinator::map(parse_id, |id| Spanned {
+            body: Cow::Borrowed(id.body.0),
+            span: id.span,
+        }),
+        parse_str,
+    ))(input)
+}
+
+fn parse_data_clause<'src, 'a>(input: Input<'src, 'a>) -> IResult<'src, 'a, DataClause<'src>> {
+    if let Ok((rest, kw)) = keyword("key")(input) {
+        let (rest, key) = cut(parse_id)(rest)?;
+        let span = Span {
+            start: kw.span.start,
+            end: key.span.end,
+        };
+        return Ok((
+            rest,
+            Spanned {
+                body: DataClause::Key(key),
+                span,
+            },
+        ));
+    }
+
+    if let Ok((rest, kw)) = keyword("columns")(input) {
+        let (rest, columns) = cut(bracket(parse_column))(rest)?;
+        let span = Span {
+            start: kw.span.start,
+            end: columns.span.end,
+        };
+        return Ok((
+            rest,
+            Spanned {
+                body: DataClause::Columns(columns),
+                span,
+            },
+        ));
+    }
+
+    let (rest, (kw, column, op, value)) = sequence::tuple((
+        keyword("where"),
+        cut(parse_column),
+        cut(alt((tag(Token::EqEq), tag(Token::NotEq)))),
+        cut(parse_str),
+    ))(input)?;
+
+    let kw: Spanned<Token> = kw;
+    let op: Spanned<Token> = op;
+
+    let span = Span {
+        start: kw.span.start,
+        end: value.span.end,
+    };
+
+    Ok((
+        rest,
+        Spanned {
+            body: DataClause::Where(Filter {
+                column,
+                negated: op.body == Token::NotEq,
+                value,
+            }),
+            span,
+        },
+    ))
+}
+
+/// Parses `from "<path>" { key <key>, columns [...], where <column> == "<value>", ... }`, where the clauses are optional
+/// and each column is an identifier or a string
+pub fn parse_data_source<'src, 'a>(input: Input<'src, 'a>) -> IResult<'src, 'a, DataSource<'src>> {
+    let (rest, (kw, path, clauses)) = sequence::tuple((
+        keyword("from"),
+        cut(parse_str),
+        opt(brace(parse_data_clause)),
+    ))(input)?;
+
+    let kw: Spanned<Token> = kw;
+
+    let span = Span {
+        start: kw.span.start,
+        end: clauses.as_ref().map_or(path.span.end, |c| c.span.end),
+    };
+
+    let mut source = DataSource {
+        path,
+        key: None,
+        columns: None,
+        filters: Vec::new(),
+    };
+
+    for clause in clauses.map(|c| c.body).unwrap_or_default() {
+        match clause.body {
+            DataClause::Key(key) => source.key = Some(key),
+            DataClause::Columns(columns) => source.columns = Some(columns),
+            DataClause::Where(filter) => source.filters.push(Spanned {
+                body: filter,
+                span: clause.span,
+            }),
+        }
+    }
+
+    Ok((rest, Spanned { body: source, span }))
+}
+
+/// Parses a single `row`, or a `for` loop generating a sequence of rows
+pub fn parse_table_entry<'src>(
+    columns: Vec<String>,
+) -> impl for<'a> FnMut(Input<'src, 'a>) -> IResult<'src, 'a, Vec<Spanned<Array<'src>>>> + Clone {
+    move |input| match parse_row(input) {
+        Ok((rest, row)) => {
+            let span = row.span.clone();
+            Ok((
+                rest,
+                Spanned {
+                    body: vec![row],
+                    span,
+                },
+            ))
+        }
+        Err(nom::Err::Error(_)) => parse_row_loop(input, &columns),
+        Err(e) => Err(e),
+    }
+}
+
+fn parse_range<'src, 'a>(input: Input<'src, 'a>) -> IResult<'src, 'a, core::ops::Range<u64>> {
+    match input {
+        [
+            Spanned {
+                body: Token::Identifier(id),
+                span,
+            },
+            rest @ ..,
+        ] => match id
+            .split_once("..")
+            .and_then(|(start, end)| Some((parse_int_literal(start)?, parse_int_literal(end)?)))
+        {
+            Some((start, end)) if end.saturating_sub(start) > MAX_LOOP_ROWS => {
+                Err(nom::Err::Failure(Error::create(
+                    input,
+                    ErrorContext::Context("A `for` loop may generate at most 4096 rows"),
+                )))
+            }
+            Some((start, end)) => Ok((
+                rest,
+                Spanned {
+                    body: start..end,
+                    span: span.clone(),
+                },
+            )),
+            None => Err(nom::Err::Failure(Error::create(
+                input,
+                ErrorContext::Context("Expected a range of the form `start..end`"),
+            ))),
+        },
+        rest => Err(nom::Err::Error(Error::create(
+            rest,
+            Expectation::Identifier,
+        ))),
+    }
+}
+
+struct RowOverride<'src> {
+    index: u64,
+    column: usize,
+    value: Spanned<Elem<'src>>,
+}
+
+fn parse_row_override<'src>(
+    range: core::ops::Range<u64>,
+    columns: Vec<String>,
+) -> impl for<'a> FnMut(Input<'src, 'a>) -> IResult<'src, 'a, RowOverride<'src>> + Clone {
+    move |input| {
+        let (rest, (kw, index, column, value)) = sequence::tuple((
+            keyword("override"),
+            cut(parse_int),
+            cut(parse_str),
+            cut(parse_elem),
+        ))(input)?;
+
+        let kw: Spanned<Token> = kw;
+
+        if !range.contains(&index.body) {
+            return Err(nom::Err::Failure(Error::create(
+                input,
+                ErrorContext::Context("Override index is outside of the range of the loop"),
+            )));
+        }
+
+        let Some(column) = columns.iter().position(|c| *c == column.body) else {
+            return Err(nom::Err::Failure(Error::create(
+                input,
+                ErrorContext::Context(
+                    "Override column does not match a column of the table heading",
+                ),
+            )));
+        };
+
+        let span = Span {
+            start: kw.span.start,
+            end: value.span.end,
+        };
+
+        Ok((
+            rest,
+            Spanned {
+                body: RowOverride {
+                    index: index.body,
+                    column,
+                    value,
+                },
+                span,
+            },
+        ))
+    }
+}
+
+/// Parses `for <var> in <start>..<end> row [...] { override <index> "<column>" <elem>, ... }`,
+/// producing one row for each index in the range with `{<var>}` in each cell replaced by the index.
+/// The range may hold at most [`MAX_LOOP_ROWS`] indices.
+fn parse_row_loop<'src, 'a>(
+    input: Input<'src, 'a>,
+    columns: &[String],
+) -> IResult<'src, 'a, Vec<Spanned<Array<'src>>>> {
+    let (rest, (kw, var, _, range, template)) = sequence::tuple((
+        keyword("for"),
+        cut(parse_id),
+        cut(keyword("in")),
+        cut(parse_range),
+        cut(parse_row),
+    ))(input)?;
+
+    let kw: Spanned<Token> = kw;
+
+    let (rest, overrides) = opt(brace(parse_row_override(
+        range.body.clone(),
+        columns.to_vec(),
+    )))(rest)?;
+
+    let end = overrides.as_ref().map_or(template.span.end, |o| o.span.end);
+    let span = Span {
+        start: kw.span.start,
+        end,
+    };
+
+    let overrides = overrides.map(|o| o.body).unwrap_or_default();
+
+    let rows = range
+        .body
+        .map(|idx| {
+            let value = idx.to_string();
+            let mut row = template.interpolate(var.body.0, &value).body.0;
+
+            for o in overrides.iter().filter(|o| o.body.index == idx) {
+                if let Some(cell) = row.get_mut(o.body.column) {
+                    *cell = o.body.value.clone();
+                }
+            }
+
+            Spanned {
+                body: Array(row),
+                span: span.clone(),
+            }
+        })
+        .collect();
+
+    Ok((rest, Spanned { body: rows, span }))
+}
+
 pub fn parse_row<'src, 'a>(input: Input<'src, 'a>) -> IResult<'src, 'a, Array<'src>> {
     let (rest, (t, body)) =
         sequence::tuple((tag(Token::KwRow), map_spanned(bracket(parse_elem), Array)))(input)
@@ -409,6 +868,270 @@ pub fn parse_row<'src, 'a>(input: Input<'src, 'a>) -> IResult<'src, 'a, Array<'s
     Ok((rest, body))
 }
 
+pub fn parse_encoding_field<'src, 'a>(
+    input: Input<'src, 'a>,
+) -> IResult<'src, 'a, EncodingField<'src>> {
+    let (rest, (kw, field)) = alt((
+        sequence::tuple((
+            keyword("field"),
+            combinator::map(
+                sequence::pair(cut(parse_id), cut(parse_bit_range)),
+                |(name, range)| EncodingField::Field(name, range),
+            ),
+        )),
+        sequence::tuple((
+            keyword("fixed"),
+            combinator::map(
+                sequence::pair(cut(parse_str), cut(parse_bit_range)),
+                |(bits, range)| EncodingField::Fixed(bits, range),
+            ),
+        )),
+    ))(input)?;
+
+    let kw: Spanned<Token> = kw;
+
+    let span = Span {
+        start: kw.span.start,
+        end: field.range().span.end,
+    };
+
+    Ok((rest, Spanned { body: field, span }))
+}
+
+pub fn parse_encoding<'src, 'a>(input: Input<'src, 'a>) -> IResult<'src, 'a, Encoding<'src>> {
+    let (
+        rest,
+        (
+            kw,
+            label,
+            width,
+            Spanned {
+                body: fields,
+                span: end_span,
+            },
+        ),
+    ) = sequence::tuple((
+        tag(Token::KwEncoding),
+        cut(parse_path),
+        cut(parse_bit),
+        cut(brace(parse_encoding_field)),
+    ))(input)?;
+
+    let kw: Spanned<Token> = kw;
+
+    let span = Span {
+        start: kw.span.start,
+        end: end_span.end,
+    };
+
+    Ok((
+        rest,
+        Spanned {
+            body: Encoding {
+                label,
+                width,
+                fields,
+            },
+            span,
+        },
+    ))
+}
+
+enum RegisterClause<'src> {
+    Aliases(Vec<Spanned<Id<'src>>>),
+    Properties(Vec<Spanned<Id<'src>>>),
+    Field(EncodingField<'src>),
+    /// A field without a bit range, such as `field XM`, whose position is not yet specified
+    UnspecifiedField(Spanned<Id<'src>>),
+}
+
+fn parse_register_clause<'src, 'a>(
+    input: Input<'src, 'a>,
+) -> IResult<'src, 'a, RegisterClause<'src>> {
+    let (rest, kw) = match alt((keyword("aliases"), keyword("properties")))(input) {
+        Ok(v) => v,
+        Err(nom::Err::Error(_)) => {
+            if let Ok((rest, (kw, name, _))) =
+                sequence::tuple((keyword("field"), parse_id, combinator::not(parse_bit_range)))(
+                    input,
+                )
+            {
+                let kw: Spanned<Token> = kw;
+                let span = Span {
+                    start: kw.span.start,
+                    end: name.span.end,
+                };
+                return Ok((
+                    rest,
+                    Spanned {
+                        body: RegisterClause::UnspecifiedField(name),
+                        span,
+                    },
+                ));
+            }
+            return map_spanned(parse_encoding_field, RegisterClause::Field)(input);
+        }
+        Err(e) => return Err(e),
+    };
+    let kw: Spanned<Token> = kw;
+
+    let (rest, list) = cut(bracket(parse_id))(rest)?;
+
+    let span = Span {
+        start: kw.span.start,
+        end: list.span.end,
+    };
+
+    let clause = match kw.body {
+        Token::Identifier("aliases") => RegisterClause::Aliases(list.body),
+        _ => RegisterClause::Properties(list.body),
+    };
+
+    Ok((rest, Spanned { body: clause, span }))
+}
+
+pub fn parse_register<'src, 'a>(input: Input<'src, 'a>) -> IResult<'src, 'a, Register<'src>> {
+    let (
+        rest,
+        (
+            kw,
+            name,
+            number,
+            Spanned {
+                body: clauses,
+                span: end_span,
+            },
+        ),
+    ) = sequence::tuple((
+        tag(Token::KwRegister),
+        cut(parse_id),
+        cut(parse_bit),
+        cut(brace(parse_register_clause)),
+    ))(input)?;
+
+    let kw: Spanned<Token> = kw;
+
+    let span = Span {
+        start: kw.span.start,
+        end: end_span.end,
+    };
+
+    let mut register = Register {
+        name,
+        number,
+        aliases: Vec::new(),
+        properties: Vec::new(),
+        fields: Vec::new(),
+        unspecified_fields: Vec::new(),
+    };
+
+    for clause in clauses {
+        match clause.body {
+            RegisterClause::Aliases(aliases) => register.aliases.extend(aliases),
+            RegisterClause::Properties(props) => register.properties.extend(props),
+            RegisterClause::Field(field) => register.fields.push(Spanned {
+                body: field,
+                span: clause.span,
+            }),
+            RegisterClause::UnspecifiedField(name) => register.unspecified_fields.push(name),
+        }
+    }
+
+    Ok((
+        rest,
+        Spanned {
+            body: register,
+            span,
+        },
+    ))
+}
+
+enum ExceptionClause<'src> {
+    Code(Spanned<Elem<'src>>),
+    Description(Spanned<Elem<'src>>),
+}
+
+fn parse_exception_clause<'src, 'a>(
+    input: Input<'src, 'a>,
+) -> IResult<'src, 'a, ExceptionClause<'src>> {
+    let (rest, (kw, elem)) = sequence::tuple((
+        alt((keyword("code"), keyword("description"))),
+        cut(parse_elem),
+    ))(input)?;
+
+    let kw: Spanned<Token> = kw;
+
+    let span = Span {
+        start: kw.span.start,
+        end: elem.span.end,
+    };
+
+    let clause = match kw.body {
+        Token::Identifier("code") => ExceptionClause::Code(elem),
+        _ => ExceptionClause::Description(elem),
+    };
+
+    Ok((rest, Spanned { body: clause, span }))
+}
+
+pub fn parse_exception<'src, 'a>(input: Input<'src, 'a>) -> IResult<'src, 'a, Exception<'src>> {
+    let (
+        rest,
+        (
+            kw,
+            name,
+            vector,
+            Spanned {
+                body: clauses,
+                span: end_span,
+            },
+        ),
+    ) = sequence::tuple((
+        tag(Token::KwException),
+        cut(parse_id),
+        opt(parse_bit),
+        cut(brace(parse_exception_clause)),
+    ))(input)?;
+
+    let kw: Spanned<Token> = kw;
+
+    let span = Span {
+        start: kw.span.start,
+        end: end_span.end,
+    };
+
+    let mut exception = Exception {
+        name,
+        vector,
+        code: None,
+        description: None,
+    };
+
+    for clause in clauses {
+        match clause.body {
+            ExceptionClause::Code(elem) => exception.code = Some(Box::new(elem)),
+            ExceptionClause::Description(elem) => exception.description = Some(Box::new(elem)),
+        }
+    }
+
+    Ok((
+        rest,
+        Spanned {
+            body: exception,
+            span,
+        },
+    ))
+}
+
+pub fn parse_item<'src, 'a>(input: Input<'src, 'a>) -> IResult<'src, 'a, Item<'src>> {
+    alt((
+        map_spanned(parse_encoding, Item::Encoding),
+        map_spanned(parse_register, Item::Register),
+        map_spanned(parse_exception, Item::Exception),
+        map_spanned(parse_elem, Item::Elem),
+    ))(input)
+}
+
 pub fn parse_elem<'src, 'a>(input: Input<'src, 'a>) -> IResult<'src, 'a, Elem<'src>> {
     alt((
         map_spanned(parse_table, Elem::Table),
@@ -416,3 +1139,106 @@ pub fn parse_elem<'src, 'a>(input: Input<'src, 'a>) -> IResult<'src, 'a, Elem<'s
         map_spanned(parse_str, Elem::StringLiteral),
     ))(input)
 }
+
+#[cfg(test)]
+mod tests {
+    use logos::Logos;
+
+    use super::*;
+
+    fn tokens(src: &str) -> Vec<Spanned<Token<'_>>> {
+        Token::lexer(src)
+            .spanned()
+            .map(|(tok, span)| Spanned {
+                body: tok.unwrap(),
+                span,
+            })
+            .collect()
+    }
+
+    fn cells(row: &Spanned<Array>) -> Vec<String> {
+        row.body
+            .0
+            .iter()
+            .map(|cell| match &cell.body {
+                Elem::StringLiteral(st) | Elem::MarkdownLiteral(st) => st.to_string(),
+                Elem::Table(_) => "<table>".to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parse_range_reads_bounds() {
+        let input = tokens("0..16");
+        let (rest, range) = parse_range(&input).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(range.body, 0..16);
+
+        let input = tokens("0x10..0x20");
+        assert_eq!(parse_range(&input).unwrap().1.body, 16..32);
+    }
+
+    #[test]
+    fn parse_range_rejects_malformed_and_oversized_ranges() {
+        let input = tokens("0..x");
+        assert!(matches!(parse_range(&input), Err(nom::Err::Failure(_))));
+
+        let input = tokens("\"0..16\"");
+        assert!(matches!(parse_range(&input), Err(nom::Err::Error(_))));
+
+        let input = tokens("0..4096");
+        assert!(parse_range(&input).is_ok());
+
+        let input = tokens("0..20000000");
+        assert!(matches!(parse_range(&input), Err(nom::Err::Failure(_))));
+    }
+
+    #[test]
+    fn parse_row_loop_interpolates_and_overrides() {
+        let input = tokens(r#"for n in 0..3 row ["r{n}", "{n}"] { override 1 "Name" "sp" }"#);
+        let columns = ["Number".to_string(), "Name".to_string()];
+        let (rest, rows) = parse_row_loop(&input, &columns).unwrap();
+        assert!(rest.is_empty());
+
+        let rows = rows.body.iter().map(cells).collect::<Vec<_>>();
+        assert_eq!(
+            rows,
+            [["r0", "0"], ["r1", "sp"], ["r2", "2"]].map(|r| r.map(String::from).to_vec())
+        );
+    }
+
+    #[test]
+    fn parse_row_loop_rejects_overrides_outside_the_loop() {
+        let input = tokens(r#"for n in 0..3 row ["{n}"] { override 3 "Number" "x" }"#);
+        let columns = ["Number".to_string()];
+        assert!(matches!(
+            parse_row_loop(&input, &columns),
+            Err(nom::Err::Failure(_))
+        ));
+
+        let input = tokens(r#"for n in 0..3 row ["{n}"] { override 1 "Missing" "x" }"#);
+        assert!(matches!(
+            parse_row_loop(&input, &columns),
+            Err(nom::Err::Failure(_))
+        ));
+    }
+
+    #[test]
+    fn contextual_keywords_are_names_elsewhere() {
+        let item = crate::spec_lang::parse_spanned(
+            "encoding E:y 8 { field code [7:4], field caption [3:0] }",
+        )
+        .unwrap();
+        assert!(matches!(item.body, Item::Encoding(_)));
+    }
+
+    #[test]
+    fn reserved_words_are_reported_readably() {
+        let (_, msg) =
+            crate::spec_lang::parse_spanned("encoding E:y 8 { field table [7:0] }").unwrap_err();
+        assert!(
+            msg.contains("found `table`, which is a reserved word"),
+            "{msg}"
+        );
+    }
+}
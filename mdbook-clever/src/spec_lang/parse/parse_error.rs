@@ -8,15 +8,49 @@ use super::Input;
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
 pub enum Expectation {
     Identifier,
+    Integer,
+    BitRange,
     Group,
     StringLiteral,
     MarkdownLiteral,
     LitToken(Token<'static>),
+    /// A keyword that is lexed as an identifier, from [`super::keyword`]
+    Keyword(&'static str),
 }
 
 impl core::fmt::Display for Expectation {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        todo!()
+        match self {
+            Expectation::Identifier => f.write_str("a name"),
+            Expectation::Integer => f.write_str("an integer"),
+            Expectation::BitRange => f.write_str("a bit range, such as `[7:4]`"),
+            Expectation::Group => f.write_str("`[`"),
+            Expectation::StringLiteral => f.write_str("a string"),
+            Expectation::MarkdownLiteral => f.write_str("a markdown literal"),
+            Expectation::LitToken(tok) => f.write_str(&describe_token(tok)),
+            Expectation::Keyword(kw) => write!(f, "`{kw}`"),
+        }
+    }
+}
+
+/// A description of a token for error messages, such as `` `table` `` or `a string`
+fn describe_token(tok: &Token) -> String {
+    if let Some(word) = tok.reserved_word() {
+        return format!("`{word}`");
+    }
+    match tok {
+        Token::LabelSep => "`:`".to_string(),
+        Token::Comma => "`,`".to_string(),
+        Token::EqEq => "`==`".to_string(),
+        Token::NotEq => "`!=`".to_string(),
+        Token::Bracket(_) => "`[`".to_string(),
+        Token::RightBracket => "`]`".to_string(),
+        Token::Brace(_) => "`{`".to_string(),
+        Token::RightBrace => "`}`".to_string(),
+        Token::StringLiteral(st) => format!("the string \"{st}\""),
+        Token::MarkdownLiteral(_) => "a markdown literal".to_string(),
+        Token::Identifier(id) => format!("`{id}`"),
+        _ => "a comment".to_string(),
     }
 }
 
@@ -30,6 +64,7 @@ impl<'src> PartialEq<Token<'src>> for Expectation {
             (Expectation::StringLiteral, Token::StringLiteral(_)) => true,
             (Expectation::MarkdownLiteral, Token::MarkdownLiteral(_)) => true,
             (Expectation::LitToken(tok), tok2) => tok.borrowed() == tok2.borrowed(),
+            (Expectation::Keyword(kw), Token::Identifier(id)) => kw == id,
             _ => false,
         }
     }
@@ -137,6 +172,34 @@ impl<I> ParseError<I> for Error<I> {
     }
 }
 
+/// Describes the error by what was expected and the token found instead, such as
+/// ``Expected a name, found `caption`, which is a reserved word``
+impl<'src, 'a> core::fmt::Display for Error<Input<'src, 'a>> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (input, ctx) = &self.primary;
+        let found = input.first().map(|tok| &tok.body);
+        let found_text = found.map_or_else(|| "the end of the group".to_string(), describe_token);
+
+        match ctx {
+            ErrorContext::Expectation(expected) => {
+                let expected = expected
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(" or ");
+                write!(f, "Expected {expected}, found {found_text}")?;
+                if found.is_some_and(|tok| tok.reserved_word().is_some()) {
+                    f.write_str(", which is a reserved word")?;
+                }
+                Ok(())
+            }
+            ErrorContext::Context(msg) => f.write_str(msg),
+            ErrorContext::EscapeError(_) => f.write_str("Invalid escape sequence"),
+            ErrorContext::Nom(_) | ErrorContext::Span(_) => write!(f, "Unexpected {found_text}"),
+        }
+    }
+}
+
 impl<I> ContextError<I> for Error<I> {
     fn add_context(input: I, ctx: &'static str, mut other: Self) -> Self {
         other